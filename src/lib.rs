@@ -1,2 +1,3 @@
 pub mod types;
-pub mod db;
\ No newline at end of file
+pub mod db;
+pub mod shared;
\ No newline at end of file
@@ -1,3 +1,82 @@
-fn main() {
-    println!("Hello, world!");
+use std::env;
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: engine <command>
+
+commands:
+    version, --version    print the engine version
+    help, --help          print this message";
+
+fn version_string() -> String {
+    format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+}
+
+fn run(args: &[String], out: &mut impl Write, err: &mut impl Write) -> ExitCode {
+    let written = match args.get(1).map(String::as_str) {
+        Some("version") | Some("--version") => writeln!(out, "{}", version_string()),
+        Some("help") | Some("--help") => writeln!(out, "{USAGE}"),
+        _ => {
+            let _ = writeln!(err, "{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match written {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(_) => ExitCode::FAILURE,
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().collect();
+    run(&args, &mut io::stdout(), &mut io::stderr())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_with(args: &[&str]) -> (ExitCode, String, String) {
+        let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let code = run(&args, &mut out, &mut err);
+        (code, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+    }
+
+    #[test]
+    fn test_version() {
+        for flag in ["version", "--version"] {
+            let (code, out, err) = run_with(&["engine", flag]);
+            assert_eq!(code, ExitCode::SUCCESS);
+            assert_eq!(out.trim(), format!("world_simulator {}", env!("CARGO_PKG_VERSION")));
+            assert!(err.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_help_and_usage() {
+        let (code, out, _) = run_with(&["engine", "--help"]);
+        assert_eq!(code, ExitCode::SUCCESS);
+        assert!(out.starts_with("usage: engine"));
+
+        let (code, out, err) = run_with(&["engine"]);
+        assert_eq!(code, ExitCode::FAILURE);
+        assert!(out.is_empty());
+        assert!(err.starts_with("usage: engine"));
+
+        let (code, _, err) = run_with(&["engine", "nope"]);
+        assert_eq!(code, ExitCode::FAILURE);
+        assert!(err.starts_with("usage: engine"));
+    }
+
+    #[test]
+    fn test_run_without_file() {
+        // there is no run command yet, it must still end in usage rather than a panic
+        let (code, out, err) = run_with(&["engine", "run"]);
+        assert_eq!(code, ExitCode::FAILURE);
+        assert!(out.is_empty());
+        assert!(err.starts_with("usage: engine"));
+    }
 }
@@ -0,0 +1,93 @@
+use std::sync::{RwLock,RwLockReadGuard,RwLockWriteGuard};
+use crate::types::InMemoryRegistry;
+
+/// A registry that can be shared between threads.
+///
+/// all the maps of the inner InMemoryRegistry sit behind a single RwLock,
+/// so lookups from any number of threads run concurrently and additions take the write lock.
+/// since there is only one lock there is no lock ordering to get wrong,
+/// the one rule is to never call write while the same thread is still holding a read guard.
+///
+/// this does not implement TypeRegistery itself, not even the read side:
+/// the trait hands out references into the maps (get_class, get_property, get_property_full...)
+/// and those can not outlive the guard a method would have to take internally.
+/// instead the guards deref to InMemoryRegistry so the whole TypeRegistery api is available through them,
+/// and a reference you get stays valid for as long as you hold the guard.
+#[derive(Debug, Default)]
+pub struct SharedRegistry<'code> {
+    inner: RwLock<InMemoryRegistry<'code>>,
+}
+
+impl<'code> SharedRegistry<'code> {
+    /// Creates a new empty registry
+    pub fn new() -> Self {
+        Self::from(InMemoryRegistry::new())
+    }
+
+    /// Locks the registry for reading, blocks while a writer holds it
+    pub fn read(&self) -> RwLockReadGuard<'_, InMemoryRegistry<'code>> {
+        self.inner.read().expect("registry lock poisoned")
+    }
+
+    /// Locks the registry for writing, blocks until all readers are done
+    pub fn write(&self) -> RwLockWriteGuard<'_, InMemoryRegistry<'code>> {
+        self.inner.write().expect("registry lock poisoned")
+    }
+
+    pub fn into_inner(self) -> InMemoryRegistry<'code> {
+        self.inner.into_inner().expect("registry lock poisoned")
+    }
+}
+
+impl<'code> From<InMemoryRegistry<'code>> for SharedRegistry<'code> {
+    fn from(reg: InMemoryRegistry<'code>) -> Self {
+        Self { inner: RwLock::new(reg) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{TypeRegistery,Type,setup_class};
+    use std::thread;
+
+    const NAMES: [&str; 8] = ["C0", "C1", "C2", "C3", "C4", "C5", "C6", "C7"];
+
+    #[test]
+    fn test_concurrent_reads_and_writes() {
+        let shared = SharedRegistry::new();
+        let a_id = setup_class(&mut *shared.write(), "A", vec![], vec![("a1", Type::Int)]).unwrap();
+
+        thread::scope(|s| {
+            s.spawn(|| {
+                for name in NAMES {
+                    setup_class(&mut *shared.write(), name, vec![a_id], vec![]).unwrap();
+                }
+            });
+
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..100 {
+                        let reg = shared.read();
+                        assert!(reg.get_class(a_id).unwrap().accessble_properties.contains_key("a1"));
+
+                        // whatever has been added so far is fully resolved
+                        for name in NAMES {
+                            if let Some(id) = reg.get_class_id(name) {
+                                let meta = reg.get_class(id).unwrap();
+                                assert!(meta.ancestors.contains(&a_id));
+                                assert!(meta.accessble_properties.contains_key("a1"));
+                            }
+                        }
+                    }
+                });
+            }
+        });
+
+        let reg = shared.into_inner();
+        for name in NAMES {
+            let id = reg.get_class_id(name).unwrap();
+            assert!(reg.is_subclass_of(id, a_id));
+        }
+    }
+}
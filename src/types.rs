@@ -1,20 +1,146 @@
-use std::hash::Hash;
-use std::collections::{HashSet,HashMap};
+use std::hash::{Hash,Hasher};
+use std::mem;
+use std::fmt;
+use std::collections::{HashSet,HashMap,BTreeMap,BTreeSet};
 use std::collections::hash_map::Entry;
+use std::collections::btree_map;
 
 #[derive(Debug)]
 pub struct DuplicateDef;
 
+impl fmt::Display for DuplicateDef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "this id is already defined")
+    }
+}
+
+impl std::error::Error for DuplicateDef {}
+
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum PropertyError{
+    /// the property was already defined
+    Duplicate,
+    /// the default value is not of the property's type
+    MismatchedDefault{
+        expected: Type,
+        found: Type,
+    },
+    /// the property is stored under a different id than its own
+    MismatchedId{
+        expected: PropertyID,
+        found: PropertyID,
+    },
+    /// the property's source class was never handed an id
+    UnknownSource(ClassID),
+    /// the id was never handed out by add_property_id
+    UnknownId(PropertyID),
+}
+
+impl From<DuplicateDef> for PropertyError {
+    fn from(_: DuplicateDef) -> Self {
+        PropertyError::Duplicate
+    }
+}
+
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum ClassError{
+    /// a parent id that has no registered ClassMeta
+    UnknownParent(ClassID),
+    /// the class would end up as its own ancestor
+    CyclicInheritance,
+    /// the class is still a parent of the contained class
+    StillParentOf(ClassID),
+    /// a property shadows an inherited one with a type that can't stand in for it
+    IncompatibleOverride{
+        name: String,
+        parent_type: Type,
+        child_type: Type,
+    },
+    /// a parent name that is not a defined class
+    UnknownClassName(String),
+    /// a property type name that is neither a primitive nor a class
+    UnknownTypeName(String),
+    /// a class with this name is already defined
+    DuplicateClass(String),
+    /// a class definition lists the same property name twice
+    DuplicateProperty(String),
+    /// a class id that has no registered ClassMeta
+    UnknownClass(ClassID),
+    /// more accessible, clashing and shadowed properties than ClassLimits allows
+    TooManyProperties{
+        count: usize,
+        limit: usize,
+    },
+    /// a property name is inherited from multiple sources and ClashPolicy::Error was asked for
+    AmbiguousProperty(String),
+    /// there is no C3 linearization of this class's ancestors
+    InconsistentHierarchy(ClassID),
+    /// more ancestors than ClassLimits allows
+    TooManyAncestors{
+        count: usize,
+        limit: usize,
+    },
+    /// every class or property id has been handed out
+    IdSpaceExhausted,
+    /// Object is the root of every hierarchy and can't be removed
+    RemoveObject,
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum RegistryError{
+    /// every class or property id has been handed out
+    IdSpaceExhausted,
+}
+
+impl From<RegistryError> for ClassError {
+    fn from(_: RegistryError) -> Self {
+        ClassError::IdSpaceExhausted
+    }
+}
+
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum RenameError{
+    /// the id was never handed out or its class was removed
+    UnknownClass(ClassID),
+    /// another class already has the new name
+    NameTaken(String),
+}
+
 //we assume 64bit word size
 pub type ClassID = u32;
 pub type PropertyID = u32;
+pub type FunctionID = u32;
+pub type TupleID = u32;
+pub type ArrayID = u32;
+
+/// the implicit root every class inherits from
+pub const OBJECT_ID: ClassID = 0;
+pub const OBJECT_NAME: &str = "Object";
+
+/// how many aliases resolve_type follows before giving up on a cycle
+pub const MAX_ALIAS_DEPTH: usize = 64;
 
 pub trait TypeRegistery<'code>{
     fn get_class(&self,id:ClassID) -> Option<&ClassMeta<'code>>{
         self.get_class_and_name(id).map(|x| x.0)
     }
+    /// looks up a type by name, tuples like "(int, string)" and arrays like "int[]" are only found if already registered
     fn get_type(&self,name:&str) -> Option<Type>;
-    fn get_property(&self,id:PropertyID) -> Option<&Property>{
+    /// like get_type but registers any tuple types it needs along the way
+    fn parse_type(&mut self,name:&str) -> Option<Type>{
+        if let Some(elem) = name.trim().strip_suffix("[]") {
+            let elem = self.parse_type(elem)?;
+            return Some(Type::Array(self.add_array(elem)));
+        }
+        match split_tuple(name) {
+            Some(parts) => {
+                let elems = parts.into_iter().map(|part| self.parse_type(part)).collect::<Option<Vec<_>>>()?;
+                Some(Type::Tuple(self.add_tuple(elems)))
+            },
+            None => self.get_type(name.trim()),
+        }
+    }
+    fn get_property(&self,id:PropertyID) -> Option<&Property<'code>>{
         self.get_property_and_name(id).map(|x| x.0)
 
     }
@@ -22,19 +148,351 @@ pub trait TypeRegistery<'code>{
     fn get_cur_class_id(&self) -> ClassID;
     fn get_cur_property_id(&self) -> PropertyID;
 
+    /// number of classes with a ClassMeta, including Object
+    fn class_count(&self) -> usize;
+    /// number of class names handed an id, defined or not
+    fn reserved_class_count(&self) -> usize;
+    /// number of properties that were given a type,
+    /// ids from add_property_id that add_property has not filled in yet are not counted
+    fn property_count(&self) -> usize;
+
     fn get_class_id(&self,name:&str) -> Option<ClassID>;
+    /// true if add_class_id handed out this id, whether or not the class is defined yet
+    fn has_class_id(&self,id:ClassID) -> bool;
     fn get_property_id(&self,name:&str,class:ClassID) -> Option<PropertyID>;
 
-    fn add_class_id(&mut self,name:&'code str) -> ClassID;
-    fn add_property_id(&mut self,name:&'code str,class:ClassID) -> PropertyID;
+    /// like try_add_class_id but panics once the ids run out
+    fn add_class_id(&mut self,name:&'code str) -> ClassID{
+        self.try_add_class_id(name).expect("ran out of class ids")
+    }
+    /// like try_add_property_id but panics once the ids run out
+    fn add_property_id(&mut self,name:&'code str,class:ClassID) -> PropertyID{
+        self.try_add_property_id(name,class).expect("ran out of property ids")
+    }
+
+    fn try_add_class_id(&mut self,name:&'code str) -> Result<ClassID,RegistryError>;
+    fn try_add_property_id(&mut self,name:&'code str,class:ClassID) -> Result<PropertyID,RegistryError>;
 
     fn add_class(&mut self,id:ClassID,value:ClassMeta<'code>) -> Result<(),DuplicateDef>;
-    fn add_property(&mut self,id:PropertyID,value:Property) -> Result<(),DuplicateDef>;
+    fn add_property(&mut self,id:PropertyID,value:Property<'code>) -> Result<(),DuplicateDef>;
+
+    /// like add_property but first checks the property is consistent with the registry
+    /// so its id has to be the one it is stored under and its source has to be a known class
+    fn add_property_checked(&mut self,id:PropertyID,value:Property<'code>) -> Result<(),PropertyError>{
+        if value.id != id {
+            return Err(PropertyError::MismatchedId{ expected: id, found: value.id });
+        }
+        if !self.has_class_id(value.source) {
+            return Err(PropertyError::UnknownSource(value.source));
+        }
+        if self.get_property_and_name(id).is_none() {
+            return Err(PropertyError::UnknownId(id));
+        }
+        self.add_property(id,value)?;
+        Ok(())
+    }
+
+    /// like add_property but also attaches annotations such as @readonly, names are given without the @
+    fn add_property_with_attributes(&mut self,id:PropertyID,value:Property<'code>,attributes:&[&'code str]) -> Result<(),DuplicateDef>{
+        self.add_property(id,value)?;
+        for attr in attributes {
+            self.add_attribute(id,attr);
+        }
+        Ok(())
+    }
+
+    /// attaches an annotation to a property, false if it was already there or the property id is unknown
+    /// annotations live in a side table so Property stays Copy
+    fn add_attribute(&mut self,id:PropertyID,name:&'code str) -> bool;
+    /// sorted annotation names of a property
+    fn get_attributes(&self,id:PropertyID) -> Vec<&'code str>;
+    fn property_has_attr(&self,id:PropertyID,name:&str) -> bool{
+        self.get_attributes(id).contains(&name)
+    }
+
+    /// like add_property but also attaches a default which has to match the property's type
+    fn add_property_with_default(&mut self,id:PropertyID,value:Property<'code>,default:DefaultValue<'code>) -> Result<(),PropertyError>{
+        if default.get_type() != value.inner_type {
+            return Err(PropertyError::MismatchedDefault{
+                expected: value.inner_type,
+                found: default.get_type(),
+            });
+        }
+        self.add_property(id,Property{default:Some(default),..value})?;
+        Ok(())
+    }
+
+    /// direct subclasses, sorted by id
+    fn children(&self,id:ClassID) -> Vec<ClassID>;
+    /// every class that inherits from this one, sorted by id
+    fn descendants(&self,id:ClassID) -> Vec<ClassID>{
+        let mut seen = BTreeSet::new();
+        let mut todo = self.children(id);
+        while let Some(child) = todo.pop() {
+            if seen.insert(child) {
+                todo.extend(self.children(child));
+            }
+        }
+        seen.into_iter().collect()
+    }
+
+    /// drops every class, property and side table type and starts over with just Object,
+    /// so a registry can be reused without reallocating.
+    /// all ids handed out before are invalid afterwards and will be handed out again
+    fn clear(&mut self);
+
+    /// gives a class a new name, everything refers to it by id so nothing else has to change
+    fn rename_class(&mut self,id:ClassID,new_name:&'code str) -> Result<(),RenameError>;
+
+    /// removes a class along with its name and its own properties
+    /// refuses to remove Object and classes that are still parents of another class
+    fn remove_class(&mut self,id:ClassID) -> Result<Option<ClassMeta<'code>>,ClassError>;
+    /// removes a property and its name entry
+    /// classes that already inherited it are not updated
+    fn remove_property(&mut self,id:PropertyID) -> Option<Property<'code>>;
+
+    /// registers a function signature, identical signatures share an id
+    fn add_function(&mut self,sig:FnSig) -> FunctionID;
+    fn get_function(&self,id:FunctionID) -> Option<&FnSig>;
+
+    /// registers a tuple type, identical element lists share an id
+    fn add_tuple(&mut self,elems:Vec<Type>) -> TupleID;
+    fn get_tuple(&self,id:TupleID) -> Option<&[Type]>;
+    fn find_tuple(&self,elems:&[Type]) -> Option<TupleID>;
+
+    /// registers an array type by its element, each element type gets one id
+    fn add_array(&mut self,elem:Type) -> ArrayID;
+    fn get_array(&self,id:ArrayID) -> Option<Type>;
+    fn find_array(&self,elem:Type) -> Option<ArrayID>;
+
+    /// makes the class id a name for target instead of a real class
+    fn add_alias(&mut self,id:ClassID,target:Type) -> Result<(),DuplicateDef>;
+    fn get_alias(&self,id:ClassID) -> Option<Type>;
+
+    /// unknown classes are not abstract
+    fn is_abstract(&self,id:ClassID) -> bool{
+        self.get_class(id).is_some_and(|meta| meta.is_abstract)
+    }
+
+    /// follows aliases down to the type they stand for
+    /// gives Invalid when the chain is longer than MAX_ALIAS_DEPTH which means it loops
+    fn resolve_type(&self,t:Type) -> Type{
+        let mut cur = t;
+        // one more step than there are aliases to see what the last one points at
+        for _ in 0..=MAX_ALIAS_DEPTH {
+            match cur {
+                Type::Class(id) => match self.get_alias(id) {
+                    Some(target) => cur = target,
+                    None => return cur,
+                },
+                _ => return cur,
+            }
+        }
+        Type::Invalid
+    }
 
     fn get_class_and_name(&self,id:ClassID) -> Option<(&ClassMeta<'code>,&'code str)>;
-    fn get_property_and_name(&self,id:PropertyID) -> Option<(&Property,&'code str)>;
+    fn get_property_and_name(&self,id:PropertyID) -> Option<(&Property<'code>,&'code str)>;
+    /// the property, its name and the name of the class that declared it, for printing A::name
+    /// None if the property or its source class is not defined yet
+    fn get_property_full(&self,id:PropertyID) -> Option<(&Property<'code>,&'code str,&'code str)>{
+        let (prop,name) = self.get_property_and_name(id)?;
+        let (_,class_name) = self.get_class_and_name(prop.source)?;
+        Some((prop,name,class_name))
+    }
+
+    /// checks if giving class these parents would make it its own ancestor
+    fn would_create_cycle(&self,class:ClassID,new_parents:&[ClassID]) -> bool{
+        new_parents.iter().any(|p| {
+            *p == class || self.get_class(*p).is_some_and(|meta| meta.ancestors.contains(&class))
+        })
+    }
+
+    /// true when sub is sup or inherits from it (directly or not)
+    fn is_subclass_of(&self,sub:ClassID,sup:ClassID) -> bool{
+        sub == sup || self.get_class(sub).is_some_and(|meta| meta.ancestors.contains(&sup))
+    }
+
+    /// the most derived class both a and b can be upcasted to
+    /// returns None when there is no such class or when there are multiple incomparable ones
+    fn common_ancestor(&self,a:ClassID,b:ClassID) -> Option<ClassID>{
+        let a_meta = self.get_class(a)?;
+        let b_meta = self.get_class(b)?;
 
+        let mut a_set = a_meta.ancestors.clone();
+        a_set.insert(a);
+        let mut b_set = b_meta.ancestors.clone();
+        b_set.insert(b);
 
+        let candidates : Vec<(ClassID,&ClassMeta<'code>)> = a_set.intersection(&b_set)
+            .filter_map(|id| self.get_class(*id).map(|meta| (*id,meta)))
+            .collect();
+
+        let (best,best_meta) = candidates.iter()
+            .max_by_key(|(_,meta)| meta.ancestors.len())
+            .copied()?;
+
+        //every other candidate has to be a base of the best one otherwise its ambigous
+        if candidates.iter().all(|(id,_)| *id == best || best_meta.ancestors.contains(id)) {
+            Some(best)
+        } else {
+            None
+        }
+    }
+
+    /// finds the property called name on class that was declared by from
+    /// this is how clashing (and shadowed) properties can still be accessed
+    fn resolve_property(&self,class:ClassID,name:&str,from:ClassID) -> Option<Property<'code>>{
+        let meta = self.get_class(class)?;
+
+        if let Some(prop) = meta.accessble_properties.get(name) {
+            if prop.source == from {
+                return Some(*prop);
+            }
+        }
+
+        meta.clashing_properties.get(name).into_iter()
+            .chain(meta.shadowed_properties.get(name))
+            .flatten()
+            .find(|prop| prop.source == from)
+            .copied()
+    }
+
+    /// what obj.name refers to for an object of this class
+    fn resolve_member(&self,class:ClassID,name:&str) -> MemberResolution<'code>{
+        let Some(meta) = self.get_class(class) else {
+            return MemberResolution::NotFound;
+        };
+
+        if let Some(prop) = meta.accessble_properties.get(name) {
+            MemberResolution::Found(*prop)
+        } else if let Some(props) = meta.clashing_properties.get(name) {
+            MemberResolution::Ambiguous(props.clone())
+        } else {
+            MemberResolution::NotFound
+        }
+    }
+
+    /// checks a resolved class against size limits, meant as a guardrail for generated schemas
+    fn validate_class(&self,id:ClassID,limits:&ClassLimits) -> Result<(),ClassError>{
+        let meta = self.get_class(id).ok_or(ClassError::UnknownClass(id))?;
+
+        let count = meta.accessble_properties.len()
+            + meta.clashing_properties.values().map(BTreeSet::len).sum::<usize>()
+            + meta.shadowed_properties.values().map(BTreeSet::len).sum::<usize>();
+        if count > limits.max_properties {
+            return Err(ClassError::TooManyProperties{ count, limit: limits.max_properties });
+        }
+
+        let count = meta.ancestors.len();
+        if count > limits.max_ancestors {
+            return Err(ClassError::TooManyAncestors{ count, limit: limits.max_ancestors });
+        }
+        Ok(())
+    }
+
+    /// whether obj.name is declared on the class itself or comes from an ancestor
+    /// None when the name is missing or clashing
+    fn property_origin(&self,class:ClassID,name:&str) -> Option<PropertyOrigin>{
+        let prop = self.get_class(class)?.accessble_properties.get(name)?;
+        if prop.source == class {
+            Some(PropertyOrigin::Direct)
+        } else {
+            Some(PropertyOrigin::Inherited(prop.source))
+        }
+    }
+
+    /// every member of a class in one list, sorted by name
+    /// names with multiple clashing or shadowed properties show up once per property
+    fn all_properties(&self,class:ClassID) -> Vec<(&'code str,&Property<'code>,PropertyStatus)>{
+        let Some(meta) = self.get_class(class) else {
+            return Vec::new();
+        };
+
+        let mut ans : Vec<_> = meta.accessble_properties.iter()
+            .map(|(name,prop)| (*name,prop,PropertyStatus::Accessible))
+            .collect();
+        for (map,status) in [(&meta.clashing_properties,PropertyStatus::Clashing),(&meta.shadowed_properties,PropertyStatus::Shadowed)] {
+            ans.extend(map.iter().flat_map(|(name,props)| props.iter().map(move |prop| (*name,prop,status))));
+        }
+
+        ans.sort_by_key(|(name,prop,status)| (*name,*status,prop.source,prop.id));
+        ans
+    }
+
+    /// C3 method resolution order, starting with the class itself and ending in Object.
+    /// parents are taken in declaration order
+    fn ancestors_ordered(&self,id:ClassID) -> Result<Vec<ClassID>,ClassError>{
+        linearize(self,id,&mut HashMap::new())
+    }
+
+    /// bytes needed to store a value of this type inline
+    /// a class is the sum of its accessible properties, a class that contains itself
+    /// is stored by reference where it loops back so the size stays finite
+    fn type_size(&self,t:Type) -> usize{
+        type_size_memo(self,t,&mut HashMap::new(),&mut HashSet::new())
+    }
+
+}
+
+fn linearize<'code>(reg: &(impl TypeRegistery<'code> + ?Sized), id: ClassID, memo: &mut HashMap<ClassID, Vec<ClassID>>) -> Result<Vec<ClassID>, ClassError> {
+    if let Some(ans) = memo.get(&id) {
+        return Ok(ans.clone());
+    }
+    let meta = reg.get_class(id).ok_or(ClassError::UnknownClass(id))?;
+
+    let parents = meta.parents.clone();
+    let mut lists: Vec<Vec<ClassID>> = Vec::new();
+    for p in &parents {
+        lists.push(linearize(reg, *p, memo)?);
+    }
+    lists.push(parents);
+
+    // take the first head that is not in the tail of any list
+    let mut ans = vec![id];
+    while lists.iter().any(|l| !l.is_empty()) {
+        let head = lists.iter()
+            .filter_map(|l| l.first())
+            .find(|h| !lists.iter().any(|l| l.get(1..).unwrap_or_default().contains(h)))
+            .copied()
+            .ok_or(ClassError::InconsistentHierarchy(id))?;
+        ans.push(head);
+        for l in &mut lists {
+            if l.first() == Some(&head) {
+                l.remove(0);
+            }
+        }
+    }
+
+    memo.insert(id, ans.clone());
+    Ok(ans)
+}
+
+fn type_size_memo<'code>(reg: &(impl TypeRegistery<'code> + ?Sized), t: Type, memo: &mut HashMap<ClassID, usize>, visiting: &mut HashSet<ClassID>) -> usize {
+    match reg.resolve_type(t) {
+        Type::Int => 4,
+        Type::Float => 8,
+        Type::String | Type::Function(_) | Type::Array(_) => mem::size_of::<usize>(),
+        Type::Invalid => 0,
+        Type::Tuple(id) => reg.get_tuple(id)
+            .map_or(0, |elems| elems.iter().map(|e| type_size_memo(reg, *e, memo, visiting)).sum()),
+        Type::Class(id) => {
+            if let Some(size) = memo.get(&id) {
+                return *size;
+            }
+            if !visiting.insert(id) {
+                return mem::size_of::<usize>();
+            }
+            let size = reg.get_class(id).map_or(0, |meta| {
+                meta.accessble_properties.values()
+                    .map(|prop| type_size_memo(reg, prop.inner_type, memo, visiting))
+                    .sum()
+            });
+            visiting.remove(&id);
+            memo.insert(id, size);
+            size
+        },
+    }
 }
 
 
@@ -48,6 +506,50 @@ pub enum Type{
 
     #[default] 
     Invalid=4,
+
+    /// index into the registry's function table
+    Function(FunctionID)=5,
+
+    /// index into the registry's tuple table
+    Tuple(TupleID)=6,
+
+    /// index into the registry's array table
+    Array(ArrayID)=7,
+}
+
+/// splits "(a, b, c)" into its top level elements, None if name is not a tuple
+fn split_tuple(name: &str) -> Option<Vec<&str>> {
+    let inner = name.trim().strip_prefix('(')?.strip_suffix(')')?;
+    if inner.trim().is_empty() {
+        return Some(Vec::new());
+    }
+
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.checked_sub(1)?,
+            ',' if depth == 0 => {
+                parts.push(inner[start..i].trim());
+                start = i + 1;
+            },
+            _ => {},
+        }
+    }
+    if depth != 0 {
+        return None;
+    }
+    parts.push(inner[start..].trim());
+    Some(parts)
+}
+
+/// the signature of a method, stored in a side table so Type stays 8 bytes
+#[derive(Debug,Clone,PartialEq,Eq,Hash)]
+pub struct FnSig{
+    pub params: Vec<Type>,
+    pub ret: Type,
 }
 
 impl Type{
@@ -55,6 +557,69 @@ impl Type{
     pub fn is_valid(self) -> bool {
         !matches!(self,Type::Invalid)
     }
+
+    /// int, float or string
+    #[inline]
+    pub fn is_primitive(self) -> bool {
+        matches!(self,Type::Int | Type::Float | Type::String)
+    }
+
+    #[inline]
+    pub fn is_class(self) -> bool {
+        matches!(self,Type::Class(_))
+    }
+
+    #[inline]
+    pub fn as_class_id(self) -> Option<ClassID> {
+        match self {
+            Type::Class(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    /// checks if a value of this type can be used where target is expected
+    pub fn is_assignable_to<'code>(self,target:Type,reg:&impl TypeRegistery<'code>) -> bool {
+        match (self,target) {
+            (Type::Invalid,_) | (_,Type::Invalid) => false,
+            (Type::Class(sub),Type::Class(sup)) => reg.is_subclass_of(sub,sup),
+            (Type::Class(_),_) | (_,Type::Class(_)) => false,
+            (Type::Function(a),Type::Function(b)) => {
+                if a == b {
+                    return true;
+                }
+                let (Some(a),Some(b)) = (reg.get_function(a),reg.get_function(b)) else {
+                    return false;
+                };
+                //arguments go the other way around from the return value
+                a.params.len() == b.params.len()
+                    && a.params.iter().zip(&b.params).all(|(x,y)| y.is_assignable_to(*x,reg))
+                    && a.ret.is_assignable_to(b.ret,reg)
+            },
+            (Type::Function(_),_) | (_,Type::Function(_)) => false,
+            (Type::Tuple(a),Type::Tuple(b)) => {
+                if a == b {
+                    return true;
+                }
+                let (Some(a),Some(b)) = (reg.get_tuple(a),reg.get_tuple(b)) else {
+                    return false;
+                };
+                a.len() == b.len() && a.iter().zip(b).all(|(x,y)| x.is_assignable_to(*y,reg))
+            },
+            (Type::Tuple(_),_) | (_,Type::Tuple(_)) => false,
+            (Type::Array(a),Type::Array(b)) => {
+                if a == b {
+                    return true;
+                }
+                let (Some(a),Some(b)) = (reg.get_array(a),reg.get_array(b)) else {
+                    return false;
+                };
+                //arrays can be written through so the elements have to fit both ways
+                a.is_assignable_to(b,reg) && b.is_assignable_to(a,reg)
+            },
+            (Type::Array(_),_) | (_,Type::Array(_)) => false,
+            (a,b) => a == b,
+        }
+    }
 }
 
 impl From<Type> for u64 {
@@ -68,16 +633,185 @@ impl From<Type> for u64 {
             Type::Class(id) => 3u64 | ((id as u64) << 32),
             
             Type::Invalid => 4,
+
+            Type::Function(id) => 5u64 | ((id as u64) << 32),
+            Type::Tuple(id) => 6u64 | ((id as u64) << 32),
+            Type::Array(id) => 7u64 | ((id as u64) << 32),
+        }
+    }
+}
+
+impl Type {
+    /// primitives by discriminant, then classes and functions by id, Invalid last
+    fn sort_key(self) -> (u32, u32) {
+        match self {
+            Type::Int => (0, 0),
+            Type::Float => (1, 0),
+            Type::String => (2, 0),
+            Type::Class(id) => (3, id),
+            Type::Function(id) => (4, id),
+            Type::Tuple(id) => (5, id),
+            Type::Array(id) => (6, id),
+            Type::Invalid => (7, 0),
         }
     }
 }
 
+impl Ord for Type {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.sort_key().cmp(&other.sort_key())
+    }
+}
+
+impl PartialOrd for Type {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl From<Type> for usize {
     fn from(t: Type) -> Self {
         u64::from(t) as usize
     }
 }
 
+impl TryFrom<u64> for Type {
+    type Error = u64;
+
+    /// inverse of From<Type> for u64, gives back the raw value if its not a valid encoding
+    fn try_from(raw: u64) -> Result<Self, u64> {
+        match raw & 0xFFFFFFFF {
+            0 if raw == 0 => Ok(Type::Int),
+            1 if raw == 1 => Ok(Type::Float),
+            2 if raw == 2 => Ok(Type::String),
+            3 => Ok(Type::Class((raw >> 32) as ClassID)),
+            4 if raw == 4 => Ok(Type::Invalid),
+            5 => Ok(Type::Function((raw >> 32) as FunctionID)),
+            6 => Ok(Type::Tuple((raw >> 32) as TupleID)),
+            7 => Ok(Type::Array((raw >> 32) as ArrayID)),
+            _ => Err(raw),
+        }
+    }
+}
+
+/// indices below this go in the Vec, everything above goes in the map
+/// primitives and Invalid all land below it, classes never do since their id sits in the high bits
+pub const DENSE_TABLE_LIMIT: usize = 256;
+
+/// per type data indexed by the usize of the Type
+///
+/// small indices get O(1) lookups from a Vec that grows on demand,
+/// ids are shifted up by 32 bits so sparse high indices go to a map instead of allocating the gap
+#[derive(Debug, Clone)]
+pub struct PropertyTable<T> {
+    dense: Vec<Option<T>>,
+    sparse: HashMap<usize, T>,
+}
+
+impl<T> Default for PropertyTable<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> PropertyTable<T> {
+    pub fn new() -> Self {
+        Self { dense: Vec::new(), sparse: HashMap::new() }
+    }
+
+    /// stores value for t giving back whatever was there before
+    pub fn insert(&mut self, t: Type, value: T) -> Option<T> {
+        let index = usize::from(t);
+        if index >= DENSE_TABLE_LIMIT {
+            return self.sparse.insert(index, value);
+        }
+        if index >= self.dense.len() {
+            self.dense.resize_with(index + 1, || None);
+        }
+        self.dense[index].replace(value)
+    }
+
+    pub fn get(&self, t: Type) -> Option<&T> {
+        let index = usize::from(t);
+        if index >= DENSE_TABLE_LIMIT {
+            return self.sparse.get(&index);
+        }
+        self.dense.get(index)?.as_ref()
+    }
+
+    pub fn get_mut(&mut self, t: Type) -> Option<&mut T> {
+        let index = usize::from(t);
+        if index >= DENSE_TABLE_LIMIT {
+            return self.sparse.get_mut(&index);
+        }
+        self.dense.get_mut(index)?.as_mut()
+    }
+
+    pub fn remove(&mut self, t: Type) -> Option<T> {
+        let index = usize::from(t);
+        if index >= DENSE_TABLE_LIMIT {
+            return self.sparse.remove(&index);
+        }
+        self.dense.get_mut(index)?.take()
+    }
+
+    pub fn len(&self) -> usize {
+        self.dense.iter().filter(|x| x.is_some()).count() + self.sparse.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod property_table_tests {
+    use super::*;
+
+    #[test]
+    fn test_primitive_dispatch() {
+        let mut table = PropertyTable::new();
+        assert_eq!(table.get(Type::Float), None);
+
+        assert_eq!(table.insert(Type::Int, "int"), None);
+        assert_eq!(table.insert(Type::String, "string"), None);
+        assert_eq!(table.insert(Type::Int, "int2"), Some("int"));
+
+        assert_eq!(table.get(Type::Int), Some(&"int2"));
+        assert_eq!(table.get(Type::String), Some(&"string"));
+        // below the vec length but never set
+        assert_eq!(table.get(Type::Float), None);
+        // past the vec length
+        assert_eq!(table.get(Type::Invalid), None);
+
+        *table.get_mut(Type::String).unwrap() = "str";
+        assert_eq!(table.remove(Type::String), Some("str"));
+        assert_eq!(table.get(Type::String), None);
+        assert_eq!(table.len(), 1);
+    }
+
+    #[test]
+    fn test_high_class_index_is_sparse() {
+        let mut table = PropertyTable::new();
+        let big = Type::Class(u32::MAX);
+
+        table.insert(big, 7);
+        table.insert(Type::Class(1), 1);
+        table.insert(Type::Int, 0);
+
+        assert_eq!(table.get(big), Some(&7));
+        assert_eq!(table.get(Type::Class(1)), Some(&1));
+        assert_eq!(table.get(Type::Class(2)), None);
+        assert_eq!(table.get(Type::Function(1)), None);
+        assert_eq!(table.get(Type::Int), Some(&0));
+
+        // the class ids all went to the map so the vec stayed tiny
+        assert_eq!(table.dense.len(), 1);
+        assert_eq!(table.sparse.len(), 2);
+        assert_eq!(table.len(), 3);
+    }
+}
+
 
 #[cfg(test)]
 mod layout_tests {
@@ -153,42 +887,156 @@ mod layout_tests {
     fn test_enum_size() {
         assert_eq!(mem::size_of::<Type>(), 8, "Type should be exactly 8 bytes");
     }
+
+    #[test]
+    fn test_u64_round_trip() {
+        for t in [Type::Int, Type::Float, Type::String, Type::Class(0), Type::Class(42), Type::Class(u32::MAX), Type::Invalid, Type::Function(7), Type::Tuple(3), Type::Array(5)] {
+            assert_eq!(Type::try_from(u64::from(t)), Ok(t));
+        }
+        assert_eq!(Type::try_from(8), Err(8));
+        assert_eq!(Type::try_from(1 << 32), Err(1 << 32));
+    }
+
+    #[test]
+    fn test_type_predicates() {
+        let cases = [
+            (Type::Int, true, None),
+            (Type::Float, true, None),
+            (Type::String, true, None),
+            (Type::Class(3), false, Some(3)),
+            (Type::Invalid, false, None),
+            (Type::Function(3), false, None),
+            (Type::Tuple(3), false, None),
+            (Type::Array(3), false, None),
+        ];
+        for (t, primitive, class) in cases {
+            assert_eq!(t.is_primitive(), primitive, "{t:?}");
+            assert_eq!(t.is_class(), class.is_some(), "{t:?}");
+            assert_eq!(t.as_class_id(), class, "{t:?}");
+        }
+    }
+
+    #[test]
+    fn test_type_order() {
+        let mut types = vec![
+            Type::Invalid,
+            Type::Class(7),
+            Type::Function(0),
+            Type::Tuple(0),
+            Type::Array(0),
+            Type::String,
+            Type::Class(0),
+            Type::Int,
+            Type::Class(u32::MAX),
+            Type::Float,
+        ];
+        types.sort();
+        assert_eq!(types, vec![
+            Type::Int,
+            Type::Float,
+            Type::String,
+            Type::Class(0),
+            Type::Class(7),
+            Type::Class(u32::MAX),
+            Type::Function(0),
+            Type::Tuple(0),
+            Type::Array(0),
+            Type::Invalid,
+        ]);
+    }
 }
 
 
 /// A struct that manages registration of classes and properties in the simulation system
 /// using in-memory hash maps
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct InMemoryRegistry<'code> {
     // Maps class IDs to their metadata and names
     classes: HashMap<ClassID, (ClassMeta<'code>, &'code str)>,
     // Maps property IDs to their data and names
-    properties: HashMap<PropertyID, (Property, &'code str)>,
+    properties: HashMap<PropertyID, (Property<'code>, &'code str)>,
     // Maps names to class IDs for quick lookup
     class_names: HashMap<&'code str, ClassID>,
     // Maps names to property IDs for quick lookup
     property_names: HashMap<&'code str, HashMap<ClassID,PropertyID>>,
+    // Function signatures indexed by FunctionID
+    functions: Vec<FnSig>,
+    // Maps signatures back to their ID so they are only stored once
+    function_ids: HashMap<FnSig, FunctionID>,
+    // Tuple element lists indexed by TupleID, interned like functions
+    tuples: Vec<Box<[Type]>>,
+    tuple_ids: HashMap<Box<[Type]>, TupleID>,
+    // Array element types indexed by ArrayID
+    arrays: Vec<Type>,
+    array_ids: HashMap<Type, ArrayID>,
+    // Class IDs that are aliases for another type
+    aliases: HashMap<ClassID, Type>,
+    // Direct subclasses of each class, kept in sync with the parents of every ClassMeta
+    children: HashMap<ClassID, BTreeSet<ClassID>>,
+    // Member lookups remembered by resolve_member_cached, dropped whenever the class is rebuilt
+    // keyed by the pair rather than per class since that is a single hash on the hot path
+    resolution_cache: HashMap<(ClassID, &'code str), MemberResolution<'code>>,
+    // Annotations like @readonly attached to properties
+    attributes: HashMap<PropertyID, BTreeSet<&'code str>>,
     // Counters for generating new IDs
     next_class_id: ClassID,
     next_property_id: PropertyID,
 }
 
 impl InMemoryRegistry<'_> {
-    /// Creates a new empty registry
+    /// Creates a new registry containing only the Object root class
     pub fn new() -> Self {
+        let mut ans = Self::empty();
+        ans.seed_object();
+        ans
+    }
+
+    fn seed_object(&mut self) {
+        let id = self.add_class_id(OBJECT_NAME);
+        debug_assert_eq!(id, OBJECT_ID);
+        let meta = ClassMeta::new(self, OBJECT_ID, Vec::new(), HashMap::new()).unwrap();
+        self.add_class(OBJECT_ID, meta).unwrap();
+    }
+
+    /// A registry without even the root class
+    fn empty() -> Self {
         Self {
             classes: HashMap::new(),
             properties: HashMap::new(),
             class_names: HashMap::new(),
             property_names: HashMap::new(),
+            functions: Vec::new(),
+            function_ids: HashMap::new(),
+            tuples: Vec::new(),
+            tuple_ids: HashMap::new(),
+            arrays: Vec::new(),
+            array_ids: HashMap::new(),
+            aliases: HashMap::new(),
+            attributes: HashMap::new(),
+            children: HashMap::new(),
+            resolution_cache: HashMap::new(),
             next_class_id: 0,
             next_property_id: 0,
         }
     }
 }
 
+impl Default for InMemoryRegistry<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<'code> TypeRegistery<'code> for InMemoryRegistry<'code> {
     fn get_type(&self, name: &str) -> Option<Type> {
+        if let Some(elem) = name.trim().strip_suffix("[]") {
+            return self.find_array(self.get_type(elem)?).map(Type::Array);
+        }
+        if let Some(parts) = split_tuple(name) {
+            let elems = parts.into_iter().map(|part| self.get_type(part)).collect::<Option<Vec<_>>>()?;
+            return self.find_tuple(&elems).map(Type::Tuple);
+        }
+
         match name {
             "int" => Some(Type::Int),
             "float" => Some(Type::Float),
@@ -205,21 +1053,21 @@ impl<'code> TypeRegistery<'code> for InMemoryRegistry<'code> {
         self.property_names.get(name).and_then(|x| x.get(&class).copied())
     }
 
-    fn add_class_id(&mut self, name: &'code str) -> ClassID {
+    fn try_add_class_id(&mut self, name: &'code str) -> Result<ClassID, RegistryError> {
         if let Some(id) = self.get_class_id(name) {
-            return id;
+            return Ok(id);
         }
         
         let id = self.next_class_id;
-        self.next_class_id += 1;
+        self.next_class_id = id.checked_add(1).ok_or(RegistryError::IdSpaceExhausted)?;
         self.class_names.insert(name, id);
-        id
+        Ok(id)
     }
 
-    fn add_property_id(&mut self, name: &'code str,class:ClassID) -> PropertyID {
+    fn try_add_property_id(&mut self, name: &'code str,class:ClassID) -> Result<PropertyID, RegistryError> {
         
         let id = self.next_property_id;
-        self.next_property_id += 1;
+        self.next_property_id = id.checked_add(1).ok_or(RegistryError::IdSpaceExhausted)?;
         // self.property_names.insert(name, id);
         if self.property_names.entry(name)
         .or_default()
@@ -233,7 +1081,7 @@ impl<'code> TypeRegistery<'code> for InMemoryRegistry<'code> {
             Entry::Vacant(spot) => spot.insert((Property::default(),name)),
         };
 
-        id
+        Ok(id)
     }
 
     fn add_class(&mut self, id: ClassID, value: ClassMeta<'code>) -> Result<(), DuplicateDef> {
@@ -244,13 +1092,16 @@ impl<'code> TypeRegistery<'code> for InMemoryRegistry<'code> {
                 let name = self.class_names.iter()
                     .find_map(|(&name, &class_id)| if class_id == id { Some(name) } else { None })
                     .ok_or(DuplicateDef)?;
+                for parent in &value.parents {
+                    self.children.entry(*parent).or_default().insert(id);
+                }
                 entry.insert((value, name));
                 Ok(())
             }
         }
     }
 
-    fn add_property(&mut self, id: PropertyID, value: Property) -> Result<(), DuplicateDef> {
+    fn add_property(&mut self, id: PropertyID, value: Property<'code>) -> Result<(), DuplicateDef> {
         match self.properties.entry(id) {
             Entry::Occupied(mut spot) => {
                 let v  = &mut spot.get_mut().0;
@@ -268,60 +1119,801 @@ impl<'code> TypeRegistery<'code> for InMemoryRegistry<'code> {
         }
     }
 
-    fn get_class_and_name(&self, id: ClassID) -> Option<(&ClassMeta<'code>, &'code str)> {
-        self.classes.get(&id).map(|(meta, name)| (meta, *name))
+    fn rename_class(&mut self, id: ClassID, new_name: &'code str) -> Result<(), RenameError> {
+        match self.get_class_id(new_name) {
+            Some(existing) if existing == id => return Ok(()),
+            Some(_) => return Err(RenameError::NameTaken(new_name.to_string())),
+            None => {},
+        }
+        let old_name = self.class_names.iter()
+            .find_map(|(&name, &class_id)| if class_id == id { Some(name) } else { None })
+            .ok_or(RenameError::UnknownClass(id))?;
+
+        self.class_names.remove(old_name);
+        self.class_names.insert(new_name, id);
+        if let Some((_, name)) = self.classes.get_mut(&id) {
+            *name = new_name;
+        }
+        Ok(())
     }
 
-    fn get_property_and_name(&self, id: PropertyID) -> Option<(&Property, &'code str)> {
-        self.properties.get(&id).map(|(prop, name)| (prop, *name))
+    fn clear(&mut self) {
+        self.classes.clear();
+        self.properties.clear();
+        self.class_names.clear();
+        self.property_names.clear();
+        self.functions.clear();
+        self.function_ids.clear();
+        self.tuples.clear();
+        self.tuple_ids.clear();
+        self.arrays.clear();
+        self.array_ids.clear();
+        self.aliases.clear();
+        self.attributes.clear();
+        self.children.clear();
+        self.resolution_cache.clear();
+        self.next_class_id = 0;
+        self.next_property_id = 0;
+        self.seed_object();
     }
-    fn get_cur_class_id(&self) -> ClassID { self.next_class_id }
-    fn get_cur_property_id(&self) -> PropertyID { self.next_property_id}
-}
 
+    fn children(&self, id: ClassID) -> Vec<ClassID> {
+        self.children.get(&id).map_or_else(Vec::new, |children| children.iter().copied().collect())
+    }
 
+    fn remove_class(&mut self, id: ClassID) -> Result<Option<ClassMeta<'code>>, ClassError> {
+        if id == OBJECT_ID {
+            return Err(ClassError::RemoveObject);
+        }
+        if let Some(child) = self.children.get(&id).and_then(|children| children.first()) {
+            return Err(ClassError::StillParentOf(*child));
+        }
+        self.children.remove(&id);
+        self.resolution_cache.retain(|(class, _), _| *class != id);
+        if let Some((meta, _)) = self.classes.get(&id) {
+            for parent in &meta.parents {
+                if let Some(siblings) = self.children.get_mut(parent) {
+                    siblings.remove(&id);
+                }
+            }
+        }
 
-#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,Default)]
-pub struct Property{
-    pub id: PropertyID,
-	pub inner_type: Type,
-	pub source: ClassID,
-}
+        self.class_names.retain(|_, class_id| *class_id != id);
+        self.aliases.remove(&id);
 
-#[derive(Debug,Clone,PartialEq)]
-pub struct ClassMeta<'code>{
-    pub parents: HashSet<ClassID>,
+        // Properties declared on this class go with it
+        let own_props: Vec<PropertyID> = self.property_names.values()
+            .filter_map(|by_class| by_class.get(&id).copied())
+            .collect();
+        for prop_id in own_props {
+            self.remove_property(prop_id);
+        }
+
+        Ok(self.classes.remove(&id).map(|(meta, _)| meta))
+    }
+
+    fn remove_property(&mut self, id: PropertyID) -> Option<Property<'code>> {
+        let (prop, name) = self.properties.remove(&id)?;
+        self.attributes.remove(&id);
+
+        if let Entry::Occupied(mut by_class) = self.property_names.entry(name) {
+            by_class.get_mut().retain(|_, prop_id| *prop_id != id);
+            if by_class.get().is_empty() {
+                by_class.remove();
+            }
+        }
+
+        Some(prop)
+    }
+
+    fn add_attribute(&mut self, id: PropertyID, name: &'code str) -> bool {
+        if !self.properties.contains_key(&id) {
+            return false;
+        }
+        self.attributes.entry(id).or_default().insert(name)
+    }
+
+    fn get_attributes(&self, id: PropertyID) -> Vec<&'code str> {
+        self.attributes.get(&id).map_or_else(Vec::new, |attrs| attrs.iter().copied().collect())
+    }
+
+    fn add_function(&mut self, sig: FnSig) -> FunctionID {
+        if let Some(id) = self.function_ids.get(&sig) {
+            return *id;
+        }
+
+        let id = self.functions.len() as FunctionID;
+        self.functions.push(sig.clone());
+        self.function_ids.insert(sig, id);
+        id
+    }
+
+    fn get_function(&self, id: FunctionID) -> Option<&FnSig> {
+        self.functions.get(id as usize)
+    }
+
+    fn add_tuple(&mut self, elems: Vec<Type>) -> TupleID {
+        if let Some(id) = self.find_tuple(&elems) {
+            return id;
+        }
+
+        let id = self.tuples.len() as TupleID;
+        let elems: Box<[Type]> = elems.into();
+        self.tuples.push(elems.clone());
+        self.tuple_ids.insert(elems, id);
+        id
+    }
+
+    fn get_tuple(&self, id: TupleID) -> Option<&[Type]> {
+        self.tuples.get(id as usize).map(|elems| &elems[..])
+    }
+
+    fn find_tuple(&self, elems: &[Type]) -> Option<TupleID> {
+        self.tuple_ids.get(elems).copied()
+    }
+
+    fn add_array(&mut self, elem: Type) -> ArrayID {
+        if let Some(id) = self.find_array(elem) {
+            return id;
+        }
+
+        let id = self.arrays.len() as ArrayID;
+        self.arrays.push(elem);
+        self.array_ids.insert(elem, id);
+        id
+    }
+
+    fn get_array(&self, id: ArrayID) -> Option<Type> {
+        self.arrays.get(id as usize).copied()
+    }
+
+    fn find_array(&self, elem: Type) -> Option<ArrayID> {
+        self.array_ids.get(&elem).copied()
+    }
+
+    fn add_alias(&mut self, id: ClassID, target: Type) -> Result<(), DuplicateDef> {
+        if self.classes.contains_key(&id) {
+            return Err(DuplicateDef);
+        }
+        match self.aliases.entry(id) {
+            Entry::Occupied(_) => Err(DuplicateDef),
+            Entry::Vacant(spot) => {
+                spot.insert(target);
+                Ok(())
+            }
+        }
+    }
+
+    fn get_alias(&self, id: ClassID) -> Option<Type> {
+        self.aliases.get(&id).copied()
+    }
+
+    fn get_class_and_name(&self, id: ClassID) -> Option<(&ClassMeta<'code>, &'code str)> {
+        self.classes.get(&id).map(|(meta, name)| (meta, *name))
+    }
+
+    fn get_property_and_name(&self, id: PropertyID) -> Option<(&Property<'code>, &'code str)> {
+        self.properties.get(&id).map(|(prop, name)| (prop, *name))
+    }
+    fn get_cur_class_id(&self) -> ClassID { self.next_class_id }
+    fn has_class_id(&self, id: ClassID) -> bool {
+        // Removed classes give their name back so the counter alone is not enough
+        self.classes.contains_key(&id) || self.class_names.values().any(|class_id| *class_id == id)
+    }
+    fn get_cur_property_id(&self) -> PropertyID { self.next_property_id}
+
+    fn class_count(&self) -> usize { self.classes.len() }
+    fn reserved_class_count(&self) -> usize { self.class_names.len() }
+    fn property_count(&self) -> usize {
+        // A reserved id sits in the table with an Invalid type until add_property replaces it
+        self.properties.values().filter(|(prop, _)| prop.inner_type.is_valid()).count()
+    }
+}
+
+const REGISTRY_MAGIC: &[u8; 4] = b"WSRG";
+
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum DeserializeError{
+    /// the buffer does not start with the registry magic bytes
+    BadMagic,
+    /// the buffer ended in the middle of a record
+    UnexpectedEnd,
+    /// a stored name is not valid utf8
+    InvalidName,
+    /// a stored type has an unknown encoding
+    InvalidType(u64),
+    /// a stored default value has an unknown tag
+    InvalidDefault,
+    /// a stored clash policy has an unknown tag
+    InvalidClashPolicy(u8),
+    /// two class records share an id
+    DuplicateClass(ClassID),
+    /// two class records share a name
+    DuplicateName(String),
+    /// two property records share an id, or a name on the same class
+    DuplicateProperty(PropertyID),
+    /// a class or property id at or past the stored counter
+    IdOutOfRange(u32),
+    /// a function, tuple or array record repeats an earlier one
+    /// interning it would shift every later id so the buffer is rejected
+    DuplicateType,
+    /// a stored type points at a missing table entry or class, or contains itself
+    InvalidTypeReference(u64),
+    /// rebuilding a class failed
+    Class(ClassError),
+}
+
+/// little endian reader over a serialized registry
+struct ByteReader<'b>{
+    bytes: &'b [u8],
+    pos: usize,
+}
+
+impl<'b> ByteReader<'b>{
+    fn take(&mut self, n: usize) -> Result<&'b [u8], DeserializeError> {
+        let end = self.pos.checked_add(n).ok_or(DeserializeError::UnexpectedEnd)?;
+        let ans = self.bytes.get(self.pos..end).ok_or(DeserializeError::UnexpectedEnd)?;
+        self.pos = end;
+        Ok(ans)
+    }
+
+    fn u32(&mut self) -> Result<u32, DeserializeError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, DeserializeError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn ty(&mut self) -> Result<Type, DeserializeError> {
+        Type::try_from(self.u64()?).map_err(DeserializeError::InvalidType)
+    }
+
+    fn name(&mut self) -> Result<&'b str, DeserializeError> {
+        let len = self.u32()? as usize;
+        std::str::from_utf8(self.take(len)?).map_err(|_| DeserializeError::InvalidName)
+    }
+}
+
+/// a loaded type has to point at an existing table entry or a class id that was handed out
+fn check_type_ref(reg: &InMemoryRegistry, t: Type) -> Result<(), DeserializeError> {
+    let known = match t {
+        Type::Class(id) => id < reg.next_class_id,
+        Type::Function(id) => reg.get_function(id).is_some(),
+        Type::Tuple(id) => reg.get_tuple(id).is_some(),
+        Type::Array(id) => reg.get_array(id).is_some(),
+        _ => true,
+    };
+    if known { Ok(()) } else { Err(DeserializeError::InvalidTypeReference(u64::from(t))) }
+}
+
+/// the types a function, tuple or array is built from
+fn side_table_parts(reg: &InMemoryRegistry, t: Type) -> Vec<Type> {
+    match t {
+        Type::Function(id) => {
+            let sig = &reg.functions[id as usize];
+            sig.params.iter().copied().chain([sig.ret]).collect()
+        },
+        Type::Tuple(id) => reg.tuples[id as usize].to_vec(),
+        Type::Array(id) => vec![reg.arrays[id as usize]],
+        _ => Vec::new(),
+    }
+}
+
+/// every loaded side table type has to be finite, otherwise type_size and friends never return.
+/// walks the tables with an explicit stack so a long chain can't overflow ours
+fn check_type_graph(reg: &InMemoryRegistry) -> Result<(), DeserializeError> {
+    // false while a type is being walked, true once all its parts are known to be fine
+    let mut done: HashMap<Type, bool> = HashMap::new();
+    let roots = (0..reg.functions.len() as FunctionID).map(Type::Function)
+        .chain((0..reg.tuples.len() as TupleID).map(Type::Tuple))
+        .chain((0..reg.arrays.len() as ArrayID).map(Type::Array));
+    for root in roots {
+        if done.contains_key(&root) {
+            continue;
+        }
+        done.insert(root, false);
+        let mut stack = vec![(root, side_table_parts(reg, root))];
+        while let Some((t, parts)) = stack.last_mut() {
+            let Some(part) = parts.pop() else {
+                done.insert(*t, true);
+                stack.pop();
+                continue;
+            };
+            check_type_ref(reg, part)?;
+            match done.get(&part) {
+                Some(true) => {},
+                Some(false) => return Err(DeserializeError::InvalidTypeReference(u64::from(part))),
+                None if matches!(part, Type::Function(_) | Type::Tuple(_) | Type::Array(_)) => {
+                    done.insert(part, false);
+                    stack.push((part, side_table_parts(reg, part)));
+                },
+                None => {},
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_name(out: &mut Vec<u8>, name: &str) {
+    out.extend((name.len() as u32).to_le_bytes());
+    out.extend(name.as_bytes());
+}
+
+impl<'code> InMemoryRegistry<'code> {
+    /// Serializes every class and property to a portable byte buffer.
+    /// only the definitions are stored, the resolved property maps are rebuilt on load
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend(REGISTRY_MAGIC);
+        out.extend(self.next_class_id.to_le_bytes());
+        out.extend(self.next_property_id.to_le_bytes());
+
+        out.extend((self.functions.len() as u32).to_le_bytes());
+        for sig in &self.functions {
+            out.extend((sig.params.len() as u32).to_le_bytes());
+            for t in &sig.params {
+                out.extend(u64::from(*t).to_le_bytes());
+            }
+            out.extend(u64::from(sig.ret).to_le_bytes());
+        }
+
+        out.extend((self.tuples.len() as u32).to_le_bytes());
+        for elems in &self.tuples {
+            out.extend((elems.len() as u32).to_le_bytes());
+            for t in elems.iter() {
+                out.extend(u64::from(*t).to_le_bytes());
+            }
+        }
+
+        out.extend((self.arrays.len() as u32).to_le_bytes());
+        for elem in &self.arrays {
+            out.extend(u64::from(*elem).to_le_bytes());
+        }
+
+        let mut aliases: Vec<(ClassID, Type)> = self.aliases.iter().map(|(id, t)| (*id, *t)).collect();
+        aliases.sort();
+        out.extend((aliases.len() as u32).to_le_bytes());
+        for (id, target) in aliases {
+            out.extend(id.to_le_bytes());
+            out.extend(u64::from(target).to_le_bytes());
+        }
+
+        // Sorted so the same registry always produces the same bytes
+        let mut class_names: Vec<(ClassID, &str)> = self.class_names.iter().map(|(name, id)| (*id, *name)).collect();
+        class_names.sort();
+        out.extend((class_names.len() as u32).to_le_bytes());
+        for (id, name) in class_names {
+            out.extend(id.to_le_bytes());
+            write_name(&mut out, name);
+            match self.get_class(id) {
+                None => out.push(0),
+                Some(meta) => {
+                    out.push(if meta.is_abstract { 2 } else { 1 });
+                    out.push(match meta.clash_policy {
+                        ClashPolicy::Keep => 0,
+                        ClashPolicy::Error => 1,
+                        ClashPolicy::FirstParent => 2,
+                        ClashPolicy::LastParent => 3,
+                    });
+                    out.extend((meta.parents.len() as u32).to_le_bytes());
+                    for p in &meta.parents {
+                        out.extend(p.to_le_bytes());
+                    }
+                }
+            }
+        }
+
+        let mut props: Vec<(PropertyID, ClassID, &str)> = self.property_names.iter()
+            .flat_map(|(name, by_class)| by_class.iter().map(move |(class, id)| (*id, *class, *name)))
+            .collect();
+        props.sort();
+        out.extend((props.len() as u32).to_le_bytes());
+        for (id, class, name) in props {
+            let prop = self.properties[&id].0;
+            out.extend(id.to_le_bytes());
+            out.extend(class.to_le_bytes());
+            write_name(&mut out, name);
+            out.extend(u64::from(prop.inner_type).to_le_bytes());
+            out.extend(prop.source.to_le_bytes());
+            match prop.default {
+                None => out.push(0),
+                Some(DefaultValue::Int(x)) => {
+                    out.push(1);
+                    out.extend(x.to_le_bytes());
+                }
+                Some(DefaultValue::Float(x)) => {
+                    out.push(2);
+                    out.extend(x.to_bits().to_le_bytes());
+                }
+                Some(DefaultValue::String(x)) => {
+                    out.push(3);
+                    write_name(&mut out, x);
+                }
+            }
+        }
+
+        let mut attributes: Vec<(&PropertyID, &BTreeSet<&str>)> = self.attributes.iter().collect();
+        attributes.sort();
+        out.extend((attributes.len() as u32).to_le_bytes());
+        for (id, names) in attributes {
+            out.extend(id.to_le_bytes());
+            out.extend((names.len() as u32).to_le_bytes());
+            for name in names {
+                write_name(&mut out, name);
+            }
+        }
+
+        out
+    }
+
+    /// Loads a registry produced by serialize.
+    /// names are borrowed straight out of the buffer so it has to outlive the registry
+    pub fn deserialize(bytes: &'code [u8]) -> Result<Self, DeserializeError> {
+        let mut reader = ByteReader { bytes, pos: 0 };
+        if reader.take(REGISTRY_MAGIC.len()).map_err(|_| DeserializeError::BadMagic)? != REGISTRY_MAGIC {
+            return Err(DeserializeError::BadMagic);
+        }
+
+        // Object is stored in the buffer like any other class
+        let mut reg = InMemoryRegistry::empty();
+        reg.next_class_id = reader.u32()?;
+        reg.next_property_id = reader.u32()?;
+
+        for _ in 0..reader.u32()? {
+            let mut params = Vec::new();
+            for _ in 0..reader.u32()? {
+                params.push(reader.ty()?);
+            }
+            let ret = reader.ty()?;
+            let sig = FnSig { params, ret };
+            if reg.function_ids.contains_key(&sig) {
+                return Err(DeserializeError::DuplicateType);
+            }
+            reg.add_function(sig);
+        }
+
+        for _ in 0..reader.u32()? {
+            let mut elems = Vec::new();
+            for _ in 0..reader.u32()? {
+                elems.push(reader.ty()?);
+            }
+            if reg.find_tuple(&elems).is_some() {
+                return Err(DeserializeError::DuplicateType);
+            }
+            reg.add_tuple(elems);
+        }
+
+        for _ in 0..reader.u32()? {
+            let elem = reader.ty()?;
+            if reg.find_array(elem).is_some() {
+                return Err(DeserializeError::DuplicateType);
+            }
+            reg.add_array(elem);
+        }
+        check_type_graph(&reg)?;
+
+        for _ in 0..reader.u32()? {
+            let id = reader.u32()?;
+            let target = reader.ty()?;
+            check_type_ref(&reg, target)?;
+            if id >= reg.next_class_id {
+                return Err(DeserializeError::IdOutOfRange(id));
+            }
+            if reg.aliases.insert(id, target).is_some() {
+                return Err(DeserializeError::DuplicateClass(id));
+            }
+        }
+
+        let mut pending: Vec<(ClassID, Vec<ClassID>, bool, ClashPolicy)> = Vec::new();
+        let mut seen = HashSet::new();
+        for _ in 0..reader.u32()? {
+            let id = reader.u32()?;
+            if id >= reg.next_class_id {
+                return Err(DeserializeError::IdOutOfRange(id));
+            }
+            if !seen.insert(id) {
+                return Err(DeserializeError::DuplicateClass(id));
+            }
+            let name = reader.name()?;
+            if reg.class_names.insert(name, id).is_some() {
+                return Err(DeserializeError::DuplicateName(name.to_string()));
+            }
+            // 0 is a reserved id, 1 a class and 2 an abstract class, classes are followed by their clash policy
+            let kind = reader.take(1)?[0];
+            if kind != 0 {
+                let policy = match reader.take(1)?[0] {
+                    0 => ClashPolicy::Keep,
+                    1 => ClashPolicy::Error,
+                    2 => ClashPolicy::FirstParent,
+                    3 => ClashPolicy::LastParent,
+                    tag => return Err(DeserializeError::InvalidClashPolicy(tag)),
+                };
+                let mut parents = Vec::new();
+                for _ in 0..reader.u32()? {
+                    parents.push(reader.u32()?);
+                }
+                if reg.aliases.contains_key(&id) {
+                    return Err(DeserializeError::DuplicateClass(id));
+                }
+                pending.push((id, parents, kind == 2, policy));
+            }
+        }
+
+        let mut own_props: HashMap<ClassID, HashMap<&'code str, Property<'code>>> = HashMap::new();
+        for _ in 0..reader.u32()? {
+            let id = reader.u32()?;
+            let class = reader.u32()?;
+            let name = reader.name()?;
+            let inner_type = reader.ty()?;
+            let source = reader.u32()?;
+            let default = match reader.take(1)?[0] {
+                0 => None,
+                1 => Some(DefaultValue::Int(reader.u32()? as i32)),
+                2 => Some(DefaultValue::Float(f64::from_bits(reader.u64()?))),
+                3 => Some(DefaultValue::String(reader.name()?)),
+                _ => return Err(DeserializeError::InvalidDefault),
+            };
+
+            if id >= reg.next_property_id {
+                return Err(DeserializeError::IdOutOfRange(id));
+            }
+            for class_id in [class, source] {
+                if class_id >= reg.next_class_id {
+                    return Err(DeserializeError::IdOutOfRange(class_id));
+                }
+            }
+            check_type_ref(&reg, inner_type)?;
+
+            let prop = Property { id, inner_type, source, default };
+            if reg.properties.insert(id, (prop, name)).is_some()
+                || reg.property_names.entry(name).or_default().insert(class, id).is_some() {
+                return Err(DeserializeError::DuplicateProperty(id));
+            }
+            if inner_type.is_valid() {
+                own_props.entry(class).or_default().insert(name, prop);
+            }
+        }
+
+        for _ in 0..reader.u32()? {
+            let id = reader.u32()?;
+            for _ in 0..reader.u32()? {
+                let name = reader.name()?;
+                reg.add_attribute(id, name);
+            }
+        }
+
+        // Parents have to be resolved before their children
+        while !pending.is_empty() {
+            let ready = pending.iter()
+                .position(|(_, parents, _, _)| parents.iter().all(|p| reg.classes.contains_key(p)));
+            let (id, parents, is_abstract, policy) = match ready {
+                Some(i) => pending.swap_remove(i),
+                // Let ClassMeta::new report what is missing
+                None => pending.swap_remove(0),
+            };
+            let props = own_props.remove(&id).unwrap_or_default();
+            let mut meta = ClassMeta::new_with_policy(&reg, id, parents, props, policy).map_err(DeserializeError::Class)?;
+            meta.is_abstract = is_abstract;
+            reg.add_class(id, meta).map_err(|_| DeserializeError::DuplicateClass(id))?;
+        }
+
+        Ok(reg)
+    }
+}
+
+
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,PartialOrd,Ord,Default)]
+pub struct Property<'code>{
+    pub id: PropertyID,
+	pub inner_type: Type,
+	pub source: ClassID,
+    /// value used when an object is constructed without setting this property
+    pub default: Option<DefaultValue<'code>>,
+}
+
+impl Property<'_>{
+    /// equal in everything but the id, for deduplicating properties registered twice
+    pub fn same_shape(&self,other:&Property) -> bool{
+        self.inner_type == other.inner_type && self.source == other.source && self.default == other.default
+    }
+}
+
+/// the outcome of looking a member up by name
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum MemberResolution<'code>{
+    Found(Property<'code>),
+    /// more than one inherited property has this name
+    Ambiguous(BTreeSet<Property<'code>>),
+    NotFound,
+}
+
+/// where a property shows up in a ClassMeta
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,PartialOrd,Ord)]
+pub enum PropertyStatus{
+    Accessible,
+    Clashing,
+    Shadowed,
+}
+
+/// where an accessible property was declared relative to the class it was looked up on
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum PropertyOrigin{
+    Direct,
+    /// declared on this ancestor
+    Inherited(ClassID),
+}
+
+/// a constant that can be used as a property default
+#[derive(Debug,Clone,Copy)]
+pub enum DefaultValue<'code>{
+    Int(i32),
+    Float(f64),
+    String(&'code str),
+}
+
+impl DefaultValue<'_>{
+    pub fn get_type(&self) -> Type {
+        match self {
+            DefaultValue::Int(_) => Type::Int,
+            DefaultValue::Float(_) => Type::Float,
+            DefaultValue::String(_) => Type::String,
+        }
+    }
+}
+
+//floats are compared bitwise so properties can stay Eq and Hash
+impl PartialEq for DefaultValue<'_>{
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (DefaultValue::Int(a), DefaultValue::Int(b)) => a == b,
+            (DefaultValue::Float(a), DefaultValue::Float(b)) => a.to_bits() == b.to_bits(),
+            (DefaultValue::String(a), DefaultValue::String(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for DefaultValue<'_>{}
+
+impl Ord for DefaultValue<'_>{
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        use std::cmp::Ordering;
+        match (self, other) {
+            (DefaultValue::Int(a), DefaultValue::Int(b)) => a.cmp(b),
+            //total_cmp agrees with the bitwise equality above
+            (DefaultValue::Float(a), DefaultValue::Float(b)) => a.total_cmp(b),
+            (DefaultValue::String(a), DefaultValue::String(b)) => a.cmp(b),
+            (DefaultValue::Int(_), _) => Ordering::Less,
+            (_, DefaultValue::Int(_)) => Ordering::Greater,
+            (DefaultValue::Float(_), _) => Ordering::Less,
+            (_, DefaultValue::Float(_)) => Ordering::Greater,
+        }
+    }
+}
+
+impl PartialOrd for DefaultValue<'_>{
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Hash for DefaultValue<'_>{
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            DefaultValue::Int(x) => x.hash(state),
+            DefaultValue::Float(x) => x.to_bits().hash(state),
+            DefaultValue::String(x) => x.hash(state),
+        }
+    }
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub struct ClassMeta<'code>{
+    /// direct parents in declaration order, without duplicates
+    pub parents: Vec<ClassID>,
 
     /// includes all possible classes this can be downcasted to
 	pub ancestors: HashSet<ClassID>,
 
     /// properties that can be accessed via obj.name 
-	pub accessble_properties: HashMap<&'code str,Property>,
+	pub accessble_properties: BTreeMap<&'code str,Property<'code>>,
 
     /// properties where there is more than 1 correct interpetation for which to take
-	pub clashing_properties: HashMap<&'code str,HashSet<Property>>,
+	pub clashing_properties: BTreeMap<&'code str,BTreeSet<Property<'code>>>,
 
     /// properties hidden behind another property with the same name 
     /// this can happen when a class has a defined property that shares a name with a parents
     /// in that case the parents property is shadowed in that class
-    pub shadowed_properties: HashMap<&'code str,HashSet<Property>>,
+    pub shadowed_properties: BTreeMap<&'code str,BTreeSet<Property<'code>>>,
+
+    /// abstract classes take part in inheritance and resolution but can't be instantiated
+    pub is_abstract: bool,
+
+    /// how clashes were settled when the class was built, reresolve settles them the same way
+    pub clash_policy: ClashPolicy,
+}
+
+
+/// what new_with_policy does with properties inherited from more than one source
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,Default)]
+pub enum ClashPolicy{
+    /// keep them in clashing_properties like new does
+    #[default]
+    Keep,
+    /// refuse to build the class
+    Error,
+    /// take the property of the earliest parent that has it accessible
+    FirstParent,
+    /// take the property of the latest parent that has it accessible
+    LastParent,
+}
+
+/// upper bounds used by validate_class
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct ClassLimits{
+    /// counts every property in the class, including clashing and shadowed ones
+    pub max_properties: usize,
+    /// counts Object too
+    pub max_ancestors: usize,
+}
+
+impl Default for ClassLimits {
+    fn default() -> Self {
+        Self { max_properties: 4096, max_ancestors: 256 }
+    }
 }
 
+/// informational notes from resolving a class, the class is still valid
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum ClassWarning{
+    /// an own property hides inherited properties from several sources that would have clashed
+    ShadowedClash{
+        name: String,
+        /// sorted ids of the classes that declared the hidden properties
+        sources: Vec<ClassID>,
+    },
+}
 
 impl<'code> ClassMeta<'code>{
-    pub fn new(reg: &impl TypeRegistery<'code>, id: ClassID, parents: HashSet<ClassID>, new_props: HashMap<&'code str, Property>) -> Self {
+    pub fn new(reg: &impl TypeRegistery<'code>, id: ClassID, parents: Vec<ClassID>, new_props: HashMap<&'code str, Property<'code>>) -> Result<Self,ClassError> {
+        Self::new_with_warnings(reg, id, parents, new_props, &mut Vec::new())
+    }
+
+    /// same as new but also records ClassWarnings for front-ends to show
+    pub fn new_with_warnings(reg: &impl TypeRegistery<'code>, id: ClassID, mut parents: Vec<ClassID>, new_props: HashMap<&'code str, Property<'code>>, warnings: &mut Vec<ClassWarning>) -> Result<Self,ClassError> {
+        // Only the first mention of a parent counts
+        let mut seen = HashSet::new();
+        parents.retain(|p| seen.insert(*p));
+
+        // Everything but the root itself inherits from Object
+        if parents.is_empty() && id != OBJECT_ID {
+            parents.push(OBJECT_ID);
+        }
+
+        if reg.would_create_cycle(id, &parents) {
+            return Err(ClassError::CyclicInheritance);
+        }
+
         // Start with our own properties in accessible_properties
         let mut ans = ClassMeta {
-            ancestors: parents.clone(),
+            ancestors: parents.iter().copied().collect(),
             parents,
-            accessble_properties: new_props,
-            clashing_properties: HashMap::new(),
-            shadowed_properties: HashMap::new(),
+            accessble_properties: new_props.into_iter().collect(),
+            clashing_properties: BTreeMap::new(),
+            shadowed_properties: BTreeMap::new(),
+            is_abstract: false,
+            clash_policy: ClashPolicy::Keep,
         };
 
+        // Sources hidden by our own properties, used for warnings
+        let mut shadowed_here: HashMap<&'code str, HashSet<ClassID>> = HashMap::new();
+
         // Process properties from parents
         for parent_id in &ans.parents {
-            let parent = reg.get_class(*parent_id).unwrap();
+            let parent = reg.get_class(*parent_id).ok_or(ClassError::UnknownParent(*parent_id))?;
 
             // Add parent's ancestors to our ancestors
             ans.ancestors.extend(parent.ancestors.clone());
@@ -339,6 +1931,11 @@ impl<'code> ClassMeta<'code>{
                 // If we define our own property with the same name, shadow the clash
                 if ans.accessble_properties.contains_key(k) && ans.accessble_properties.get(k).unwrap().source == id {
                     // Our own property shadows the clashing properties
+                    let own = ans.accessble_properties[k];
+                    for prop in v {
+                        check_override(reg, k, own, *prop)?;
+                    }
+                    shadowed_here.entry(k).or_default().extend(v.iter().map(|p| p.source));
                     ans.shadowed_properties
                         .entry(k)
                         .or_default()
@@ -356,11 +1953,13 @@ impl<'code> ClassMeta<'code>{
             for (k, v) in &parent.accessble_properties {
                 // Check if we already have a property with this name
                 match ans.accessble_properties.entry(k) {
-                    Entry::Occupied(entry) => {
+                    btree_map::Entry::Occupied(entry) => {
                         let current_prop = entry.get();
                         
                         // If our class defines this property, shadow the parent's property
                         if current_prop.source == id {
+                            check_override(reg, k, *current_prop, *v)?;
+                            shadowed_here.entry(k).or_default().insert(v.source);
                             ans.shadowed_properties
                                 .entry(k)
                                 .or_default()
@@ -382,7 +1981,7 @@ impl<'code> ClassMeta<'code>{
                             clashing.insert(removed_prop);
                         }
                     },
-                    Entry::Vacant(entry) => {
+                    btree_map::Entry::Vacant(entry) => {
                         // If we have clashing properties with this name already, add to clash
                         if ans.clashing_properties.contains_key(k) {
                             ans.clashing_properties
@@ -398,29 +1997,233 @@ impl<'code> ClassMeta<'code>{
             }
         }
 
-        ans
+        // A clash picked up from a later parent also covers a property an earlier parent made accessible
+        for (k, v) in ans.clashing_properties.iter_mut() {
+            if let btree_map::Entry::Occupied(entry) = ans.accessble_properties.entry(k) {
+                if entry.get().source != id {
+                    v.insert(entry.remove());
+                }
+            }
+        }
+
+        let mut clashes: Vec<_> = shadowed_here.into_iter().filter(|(_, sources)| sources.len() > 1).collect();
+        clashes.sort_by_key(|(name, _)| *name);
+        for (name, sources) in clashes {
+            let mut sources: Vec<ClassID> = sources.into_iter().collect();
+            sources.sort();
+            warnings.push(ClassWarning::ShadowedClash { name: name.to_string(), sources });
+        }
+
+        Ok(ans)
+    }
+
+    /// like new but the policy decides what happens to clashes, going by the order of the parents.
+    /// the property that loses to the chosen one is moved to shadowed_properties
+    pub fn new_with_policy(reg: &impl TypeRegistery<'code>, id: ClassID, parents: Vec<ClassID>, new_props: HashMap<&'code str, Property<'code>>, policy: ClashPolicy) -> Result<Self,ClassError> {
+        let mut ans = Self::new(reg, id, parents, new_props)?;
+        ans.clash_policy = policy;
+
+        let ordered: Vec<ClassID> = match policy {
+            ClashPolicy::Keep => return Ok(ans),
+            ClashPolicy::Error => match ans.clashing_properties.keys().next() {
+                Some(name) => return Err(ClassError::AmbiguousProperty(name.to_string())),
+                None => return Ok(ans),
+            },
+            ClashPolicy::FirstParent => ans.parents.clone(),
+            ClashPolicy::LastParent => ans.parents.iter().rev().copied().collect(),
+        };
+
+        let names: Vec<&'code str> = ans.clashing_properties.keys().copied().collect();
+        for name in names {
+            // a parent that has its own clash for this name has nothing to offer
+            let chosen = ordered.iter()
+                .filter_map(|p| reg.get_class(*p)?.accessble_properties.get(name))
+                .find(|prop| ans.clashing_properties[name].contains(prop))
+                .copied();
+            if let Some(chosen) = chosen {
+                let mut losers = ans.clashing_properties.remove(name).unwrap();
+                losers.remove(&chosen);
+                ans.shadowed_properties.entry(name).or_default().extend(losers);
+                ans.accessble_properties.insert(name, chosen);
+            }
+        }
+
+        Ok(ans)
+    }
+
+    /// hash of what the class looks like rather than which id it got,
+    /// so two classes built the same way from the same parents hash equal.
+    /// own properties are hashed without their source and property ids are left out.
+    /// uses FNV-1a so the value is the same across runs and builds on one platform
+    pub fn structural_hash(&self) -> u64 {
+        let mut state = Fnv1a::default();
+        self.structure().hash(&mut state);
+        state.finish()
+    }
+
+    /// whether the two classes look the same in the sense of structural_hash
+    pub fn same_structure(&self, other: &ClassMeta<'code>) -> bool {
+        self.structure() == other.structure()
+    }
+
+    fn structure(&self) -> Structure<'_, 'code> {
+        // inherited properties keep their source since that is a real class
+        let shape = |prop: &Property<'code>| (prop.inner_type, prop.default, self.ancestors.contains(&prop.source).then_some(prop.source));
+        let accessible = self.accessble_properties.iter().map(|(name, prop)| (*name, shape(prop))).collect();
+        let grouped = [&self.clashing_properties, &self.shadowed_properties].map(|map| {
+            map.iter().map(|(name, props)| {
+                let mut shapes: Vec<_> = props.iter().map(shape).collect();
+                shapes.sort();
+                (*name, shapes)
+            }).collect()
+        });
+        Structure { parents: &self.parents, is_abstract: self.is_abstract, clash_policy: self.clash_policy, accessible, grouped }
     }
 }
 
-/// Helper function to create a property
-pub fn create_property<'a>(reg: &mut impl TypeRegistery<'a>, prop_name: &'a str, class_id: ClassID, prop_type: Type) -> Property {
-    let prop_id = reg.add_property_id(prop_name,class_id);
-    let property = Property {
-        id: prop_id,
-        inner_type: prop_type,
-        source: class_id,
-    };
-    reg.add_property(prop_id, property).unwrap();
-    property
+/// 64 bit FNV-1a, unlike DefaultHasher its output is fixed
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 = (self.0 ^ u64::from(*b)).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// the parts of a property structural_hash looks at
+type PropertyShape<'code> = (Type, Option<DefaultValue<'code>>, Option<ClassID>);
+
+/// the parts of a class structural_hash looks at
+#[derive(PartialEq,Hash)]
+struct Structure<'a, 'code>{
+    parents: &'a [ClassID],
+    is_abstract: bool,
+    clash_policy: ClashPolicy,
+    accessible: Vec<(&'code str, PropertyShape<'code>)>,
+    /// clashing then shadowed properties
+    grouped: [Vec<(&'code str, Vec<PropertyShape<'code>>)>; 2],
+}
+
+/// old to new ids, anything missing is kept as it is
+#[derive(Debug,Default)]
+struct IdMap{
+    classes: HashMap<ClassID, ClassID>,
+    properties: HashMap<PropertyID, PropertyID>,
+    functions: HashMap<FunctionID, FunctionID>,
+    tuples: HashMap<TupleID, TupleID>,
+    arrays: HashMap<ArrayID, ArrayID>,
+}
+
+impl IdMap {
+    fn class(&self, id: ClassID) -> ClassID {
+        self.classes.get(&id).copied().unwrap_or(id)
+    }
+
+    fn property_id(&self, id: PropertyID) -> PropertyID {
+        self.properties.get(&id).copied().unwrap_or(id)
+    }
+
+    fn ty(&self, t: Type) -> Type {
+        match t {
+            Type::Class(id) => Type::Class(self.class(id)),
+            Type::Function(id) => Type::Function(self.functions.get(&id).copied().unwrap_or(id)),
+            Type::Tuple(id) => Type::Tuple(self.tuples.get(&id).copied().unwrap_or(id)),
+            Type::Array(id) => Type::Array(self.arrays.get(&id).copied().unwrap_or(id)),
+            t => t,
+        }
+    }
+
+    fn property<'code>(&self, prop: Property<'code>) -> Property<'code> {
+        Property {
+            id: self.property_id(prop.id),
+            inner_type: self.ty(prop.inner_type),
+            source: self.class(prop.source),
+            default: prop.default,
+        }
+    }
+
+    fn meta<'code>(&self, meta: &ClassMeta<'code>) -> ClassMeta<'code> {
+        let props = |map: &BTreeMap<&'code str, BTreeSet<Property<'code>>>| map.iter()
+            .map(|(name, props)| (*name, props.iter().map(|p| self.property(*p)).collect()))
+            .collect();
+        ClassMeta {
+            parents: meta.parents.iter().map(|id| self.class(*id)).collect(),
+            ancestors: meta.ancestors.iter().map(|id| self.class(*id)).collect(),
+            accessble_properties: meta.accessble_properties.iter().map(|(name, p)| (*name, self.property(*p))).collect(),
+            clashing_properties: props(&meta.clashing_properties),
+            shadowed_properties: props(&meta.shadowed_properties),
+            is_abstract: meta.is_abstract,
+            clash_policy: meta.clash_policy,
+        }
+    }
+}
+
+/// shadowing is only allowed when the new property can be used wherever the old one was
+/// so class types may narrow to a subclass but never widen, and primitives have to match exactly
+fn check_override<'code>(reg: &impl TypeRegistery<'code>, name: &str, child: Property<'code>, parent: Property<'code>) -> Result<(), ClassError> {
+    if child.inner_type.is_assignable_to(parent.inner_type, reg) {
+        Ok(())
+    } else {
+        Err(ClassError::IncompatibleOverride {
+            name: name.to_string(),
+            parent_type: parent.inner_type,
+            child_type: child.inner_type,
+        })
+    }
+}
+
+/// Helper function to create a property
+pub fn create_property<'a>(reg: &mut impl TypeRegistery<'a>, prop_name: &'a str, class_id: ClassID, prop_type: Type) -> Property<'a> {
+    let prop_id = reg.add_property_id(prop_name,class_id);
+    let property = Property {
+        id: prop_id,
+        inner_type: prop_type,
+        source: class_id,
+        default: None,
+    };
+    reg.add_property_checked(prop_id, property).unwrap();
+    property
 }
 
 /// Helper function to set up a class with properties
 pub fn setup_class<'a>(
     reg: &mut impl TypeRegistery<'a>,
     class_name: &'a str,
-    parents: HashSet<ClassID>,
+    parents: Vec<ClassID>,
+    properties: Vec<(&'a str, Type)>,
+) -> Result<ClassID,ClassError> {
+    register_class(reg, class_name, parents, properties, false)
+}
+
+/// Same as setup_class but the class is marked abstract
+pub fn setup_abstract_class<'a>(
+    reg: &mut impl TypeRegistery<'a>,
+    class_name: &'a str,
+    parents: Vec<ClassID>,
+    properties: Vec<(&'a str, Type)>,
+) -> Result<ClassID,ClassError> {
+    register_class(reg, class_name, parents, properties, true)
+}
+
+fn register_class<'a>(
+    reg: &mut impl TypeRegistery<'a>,
+    class_name: &'a str,
+    parents: Vec<ClassID>,
     properties: Vec<(&'a str, Type)>,
-) -> ClassID {
+    is_abstract: bool,
+) -> Result<ClassID,ClassError> {
     let class_id = reg.add_class_id(class_name);
     
     // Create the properties for this class
@@ -430,11 +2233,492 @@ pub fn setup_class<'a>(
         props_map.insert(prop_name, property);
     }
     
-    // Create the class metadata
-    let class_meta = ClassMeta::new(reg, class_id, parents, props_map);
+    // Create the class metadata, on failure the reserved properties are handed back so a retry starts clean
+    let prop_ids: Vec<PropertyID> = props_map.values().map(|prop| prop.id).collect();
+    let mut class_meta = match ClassMeta::new(reg, class_id, parents, props_map) {
+        Ok(meta) => meta,
+        Err(err) => {
+            for id in prop_ids {
+                reg.remove_property(id);
+            }
+            return Err(err);
+        },
+    };
+    class_meta.is_abstract = is_abstract;
     reg.add_class(class_id, class_meta).unwrap();
     
-    class_id
+    Ok(class_id)
+}
+
+/// A class definition that refers to its parents and property types by name
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct ClassDef<'code>{
+    pub name: &'code str,
+    pub parents: Vec<&'code str>,
+    /// property name and type name pairs
+    pub properties: Vec<(&'code str,&'code str)>,
+    pub is_abstract: bool,
+}
+
+impl<'code> InMemoryRegistry<'code> {
+    /// Registers a batch of classes in any order, parents are always resolved before their children.
+    /// names can refer to classes in the batch or ones that are already registered.
+    /// returns the ids in the order the definitions were given.
+    ///
+    /// property types are parsed like parse_type so arrays and tuples of batch classes work.
+    /// names, parents and cycles are all checked before anything is registered,
+    /// a later failure such as an IncompatibleOverride takes the whole batch back out again.
+    /// only the interned array and tuple types stay behind in either case
+    pub fn register_all(&mut self, defs: Vec<ClassDef<'code>>) -> Result<Vec<ClassID>,ClassError> {
+        let mut index: HashMap<&str,usize> = HashMap::new();
+        for (i, def) in defs.iter().enumerate() {
+            let defined = self.get_class_id(def.name).is_some_and(|id| self.get_class(id).is_some());
+            if defined || index.insert(def.name, i).is_some() {
+                return Err(ClassError::DuplicateClass(def.name.to_string()));
+            }
+        }
+
+        let is_class = |name: &str| index.contains_key(name) || self.get_class_id(name).is_some_and(|id| self.get_class(id).is_some());
+        for def in &defs {
+            if let Some(p) = def.parents.iter().find(|p| !is_class(p)) {
+                return Err(ClassError::UnknownClassName(p.to_string()));
+            }
+            if let Some((_, t)) = def.properties.iter().find(|(_, t)| !self.knows_type_name(t, &index)) {
+                return Err(ClassError::UnknownTypeName(t.to_string()));
+            }
+            let mut names = HashSet::new();
+            if let Some((name, _)) = def.properties.iter().find(|(name, _)| !names.insert(*name)) {
+                return Err(ClassError::DuplicateProperty(name.to_string()));
+            }
+        }
+
+        // Kahn's algorithm over the parents that are part of this batch
+        let mut missing: Vec<usize> = vec![0; defs.len()];
+        let mut children: Vec<Vec<usize>> = vec![Vec::new(); defs.len()];
+        for (i, def) in defs.iter().enumerate() {
+            for p in &def.parents {
+                if let Some(&j) = index.get(p) {
+                    missing[i] += 1;
+                    children[j].push(i);
+                }
+            }
+        }
+
+        let mut order: Vec<usize> = (0..defs.len()).filter(|i| missing[*i] == 0).collect();
+        let mut next = 0;
+        while next < order.len() {
+            for &child in &children[order[next]] {
+                missing[child] -= 1;
+                if missing[child] == 0 {
+                    order.push(child);
+                }
+            }
+            next += 1;
+        }
+        if order.len() != defs.len() {
+            return Err(ClassError::CyclicInheritance);
+        }
+
+        // Names that were only reserved before the batch keep their reservation on failure
+        let reserved: Vec<bool> = defs.iter().map(|def| self.get_class_id(def.name).is_some()).collect();
+        match self.register_in_order(&defs, &order) {
+            Ok(ids) => Ok(ids),
+            Err(err) => {
+                // Children come later in the order so they go first
+                for &i in order.iter().rev() {
+                    if let Some(id) = self.get_class_id(defs[i].name) {
+                        self.remove_class(id).expect("batch children are removed before their parents");
+                        if reserved[i] {
+                            self.class_names.insert(defs[i].name, id);
+                        }
+                    }
+                }
+                Err(err)
+            },
+        }
+    }
+
+    fn register_in_order(&mut self, defs: &[ClassDef<'code>], order: &[usize]) -> Result<Vec<ClassID>,ClassError> {
+        // Reserve every id up front so property types can refer to any class in the batch
+        let ids = defs.iter().map(|def| self.try_add_class_id(def.name)).collect::<Result<Vec<ClassID>,RegistryError>>()?;
+
+        for &i in order {
+            let def = &defs[i];
+            let parents = def.parents.iter().map(|p| self.get_class_id(p).unwrap()).collect();
+            let properties = def.properties.iter().map(|(name, t)| (*name, self.parse_type(t).unwrap())).collect();
+            register_class(self, def.name, parents, properties, def.is_abstract)?;
+        }
+
+        Ok(ids)
+    }
+
+    /// whether parse_type would accept the name once the batch classes have ids
+    fn knows_type_name(&self, name: &str, batch: &HashMap<&str,usize>) -> bool {
+        if let Some(elem) = name.trim().strip_suffix("[]") {
+            return self.knows_type_name(elem, batch);
+        }
+        match split_tuple(name) {
+            Some(parts) => parts.into_iter().all(|part| self.knows_type_name(part, batch)),
+            None => batch.contains_key(name.trim()) || self.get_type(name.trim()).is_some(),
+        }
+    }
+
+    /// properties registered under this class that were given a type
+    fn own_properties(&self, id: ClassID) -> HashMap<&'code str, Property<'code>> {
+        self.property_names.iter()
+            .filter_map(|(name, by_class)| {
+                let prop = self.properties[by_class.get(&id)?].0;
+                prop.inner_type.is_valid().then_some((*name, prop))
+            })
+            .collect()
+    }
+
+    /// Rebuilds a class from its stored parents and own properties,
+    /// for picking up properties added to the class or its parents after it was resolved.
+    /// children are not touched, use reresolve_descendants for that
+    pub fn reresolve(&mut self, id: ClassID) -> Result<(), ClassError> {
+        let old = self.get_class(id).ok_or(ClassError::UnknownClass(id))?;
+        let (parents, is_abstract, policy) = (old.parents.clone(), old.is_abstract, old.clash_policy);
+
+        let mut meta = ClassMeta::new_with_policy(self, id, parents, self.own_properties(id), policy)?;
+        meta.is_abstract = is_abstract;
+        self.classes.get_mut(&id).unwrap().0 = meta;
+        self.resolution_cache.retain(|(class, _), _| *class != id);
+        Ok(())
+    }
+
+    /// same as resolve_member but remembers the answer, for hot loops that look up the same names over and over.
+    /// takes &mut self so the registry stays Sync, behind a SharedRegistry this needs the write lock
+    pub fn resolve_member_cached(&mut self, class: ClassID, name: &'code str) -> MemberResolution<'code> {
+        if let Some(ans) = self.resolution_cache.get(&(class, name)) {
+            return ans.clone();
+        }
+        let ans = self.resolve_member(class, name);
+        if self.classes.contains_key(&class) {
+            self.resolution_cache.insert((class, name), ans.clone());
+        }
+        ans
+    }
+
+    /// Renumbers classes by sorted name and properties by class and name,
+    /// so the same set of classes always ends up with the same ids no matter the registration order.
+    /// function, tuple and array ids are sorted by what they hold and the id counters are compacted,
+    /// so two registries with the same contents serialize to the same bytes.
+    /// Object keeps id 0 and every stored id is rewritten to match
+    pub fn renumber_by_name(&mut self) {
+        let mut names: Vec<(&'code str, ClassID)> = self.class_names.iter()
+            .filter(|(_, id)| **id != OBJECT_ID)
+            .map(|(name, id)| (*name, *id))
+            .collect();
+        names.sort();
+
+        let mut map = IdMap::default();
+        for (new_id, (_, old_id)) in (1..).zip(names) {
+            map.classes.insert(old_id, new_id);
+        }
+
+        let mut props: Vec<(ClassID, &'code str, PropertyID)> = self.property_names.iter()
+            .flat_map(|(name, by_class)| by_class.iter().map(|(class, id)| (map.class(*class), *name, *id)))
+            .collect();
+        props.sort();
+        let property_count = props.len() as PropertyID;
+        for (new_id, (_, _, old_id)) in (0..).zip(props) {
+            map.properties.insert(old_id, new_id);
+        }
+
+        // Side table types are ordered by their contents after the class ids changed
+        let side_order = |len: usize, wrap: fn(u32) -> Type| {
+            let mut keyed: Vec<(Vec<u64>, u32)> = (0..len as u32).map(|id| {
+                let mut key = Vec::new();
+                self.canonical_key(&map, wrap(id), &mut key);
+                (key, id)
+            }).collect();
+            keyed.sort();
+            (0..).zip(keyed).map(|(new_id, (_, old_id))| (old_id, new_id)).collect::<HashMap<u32, u32>>()
+        };
+        let (functions, tuples, arrays) = (
+            side_order(self.functions.len(), Type::Function),
+            side_order(self.tuples.len(), Type::Tuple),
+            side_order(self.arrays.len(), Type::Array),
+        );
+        (map.functions, map.tuples, map.arrays) = (functions, tuples, arrays);
+
+        self.apply(&map);
+        self.next_class_id = self.class_names.len() as ClassID;
+        self.next_property_id = property_count;
+    }
+
+    /// writes out what a type holds with classes already renumbered,
+    /// every side table type is tagged and sized so no two of them share a key
+    fn canonical_key(&self, map: &IdMap, t: Type, key: &mut Vec<u64>) {
+        match t {
+            Type::Function(id) => {
+                let sig = &self.functions[id as usize];
+                key.extend([u64::from(Type::Function(0)), sig.params.len() as u64]);
+                for p in &sig.params {
+                    self.canonical_key(map, *p, key);
+                }
+                self.canonical_key(map, sig.ret, key);
+            },
+            Type::Tuple(id) => {
+                let elems = &self.tuples[id as usize];
+                key.extend([u64::from(Type::Tuple(0)), elems.len() as u64]);
+                for e in elems.iter() {
+                    self.canonical_key(map, *e, key);
+                }
+            },
+            Type::Array(id) => {
+                key.push(u64::from(Type::Array(0)));
+                self.canonical_key(map, self.arrays[id as usize], key);
+            },
+            t => key.push(u64::from(map.ty(t))),
+        }
+    }
+
+    /// Folds another registry into this one, giving its classes and properties fresh ids here.
+    /// a class name defined in both is kept once if the two are structurally identical
+    /// and is a DuplicateClass otherwise, as is a name other defines that is only reserved here.
+    /// every check runs before anything is imported so a failed merge leaves this registry as it was,
+    /// that includes running out of ids for other's classes and properties.
+    /// returns where each of other's class ids ended up
+    pub fn merge(&mut self, other: InMemoryRegistry<'code>) -> Result<HashMap<ClassID, ClassID>, ClassError> {
+        let mut map = IdMap::default();
+        let mut next_class_id = self.next_class_id;
+        let mut names: Vec<(ClassID, &'code str)> = other.class_names.iter().map(|(name, id)| (*id, *name)).collect();
+        names.sort();
+        for (id, name) in &names {
+            let new_id = match self.get_class_id(name) {
+                Some(existing) => existing,
+                None => {
+                    let new_id = next_class_id;
+                    next_class_id = new_id.checked_add(1).ok_or(RegistryError::IdSpaceExhausted)?;
+                    new_id
+                },
+            };
+            map.classes.insert(*id, new_id);
+        }
+
+        // Compare against the ids other's side table types would get without interning them yet
+        let side_types = || (0..other.functions.len() as FunctionID).map(Type::Function)
+            .chain((0..other.tuples.len() as TupleID).map(Type::Tuple))
+            .chain((0..other.arrays.len() as ArrayID).map(Type::Array));
+        let mut preview = IdMap { classes: map.classes.clone(), ..IdMap::default() };
+        let mut fresh = (self.functions.len() as FunctionID, self.tuples.len() as TupleID, self.arrays.len() as ArrayID);
+        for t in side_types() {
+            self.preview_type(&other, &mut preview, &mut fresh, t);
+        }
+
+        for (id, name) in &names {
+            let new_id = map.class(*id);
+            if new_id >= self.next_class_id {
+                continue;
+            }
+            let same = match (self.get_alias(new_id), other.get_alias(*id)) {
+                (Some(a), Some(b)) => a == preview.ty(b),
+                (None, None) => match (self.get_class(new_id), other.get_class(*id)) {
+                    (Some(a), Some(b)) => {
+                        // the hash only rules out most mismatches quickly
+                        let b = preview.meta(b);
+                        a.structural_hash() == b.structural_hash() && a.same_structure(&b)
+                    },
+                    // our reserved properties would be reused for a class that never declared them
+                    (None, Some(_)) => false,
+                    _ => true,
+                },
+                _ => false,
+            };
+            if !same {
+                return Err(ClassError::DuplicateClass(name.to_string()));
+            }
+        }
+
+        // A property of a class both sides share keeps the id it already has here
+        let mut props: Vec<(PropertyID, ClassID, &'code str)> = other.property_names.iter()
+            .flat_map(|(name, by_class)| by_class.iter().map(|(class, id)| (*id, *class, *name)))
+            .collect();
+        props.sort();
+        let mut fresh = Vec::new();
+        let mut next_property_id = self.next_property_id;
+        for (id, class, name) in props {
+            let new_id = match self.get_property_id(name, map.class(class)) {
+                Some(existing) => existing,
+                None => {
+                    fresh.push((id, class, name));
+                    let new_id = next_property_id;
+                    next_property_id = new_id.checked_add(1).ok_or(RegistryError::IdSpaceExhausted)?;
+                    new_id
+                },
+            };
+            map.properties.insert(id, new_id);
+        }
+
+        // Side table types only need the class ids, elements get imported before their containers
+        for t in side_types() {
+            self.import_type(&other, &mut map, t);
+        }
+
+        self.next_property_id = next_property_id;
+        for (id, class, name) in fresh {
+            let new_id = map.property_id(id);
+            self.properties.insert(new_id, (map.property(other.properties[&id].0), name));
+            self.property_names.entry(name).or_default().insert(map.class(class), new_id);
+            for attr in other.get_attributes(id) {
+                self.add_attribute(new_id, attr);
+            }
+        }
+
+        self.next_class_id = next_class_id;
+        for (id, name) in names {
+            let new_id = map.class(id);
+            self.class_names.entry(name).or_insert(new_id);
+            if let Some(target) = other.get_alias(id) {
+                self.aliases.entry(new_id).or_insert(map.ty(target));
+            }
+            if let (Some(meta), false) = (other.get_class(id), self.classes.contains_key(&new_id)) {
+                self.add_class(new_id, map.meta(meta)).map_err(|_| ClassError::DuplicateClass(name.to_string()))?;
+            }
+        }
+
+        Ok(map.classes)
+    }
+
+    /// where import_type would put a side table type of other, without interning anything.
+    /// types this registry doesn't have yet get ids past the end of its tables, so they match nothing here
+    fn preview_type(&self, other: &InMemoryRegistry<'code>, map: &mut IdMap, fresh: &mut (FunctionID, TupleID, ArrayID), t: Type) -> Type {
+        let next = |counter: &mut u32| {
+            *counter += 1;
+            *counter - 1
+        };
+        match t {
+            Type::Function(id) => {
+                if let Some(new_id) = map.functions.get(&id) {
+                    return Type::Function(*new_id);
+                }
+                let sig = &other.functions[id as usize];
+                let params = sig.params.iter().map(|p| self.preview_type(other, map, fresh, *p)).collect();
+                let ret = self.preview_type(other, map, fresh, sig.ret);
+                let new_id = self.function_ids.get(&FnSig { params, ret }).copied().unwrap_or_else(|| next(&mut fresh.0));
+                map.functions.insert(id, new_id);
+                Type::Function(new_id)
+            },
+            Type::Tuple(id) => {
+                if let Some(new_id) = map.tuples.get(&id) {
+                    return Type::Tuple(*new_id);
+                }
+                let elems: Vec<Type> = other.tuples[id as usize].iter().map(|e| self.preview_type(other, map, fresh, *e)).collect();
+                let new_id = self.find_tuple(&elems).unwrap_or_else(|| next(&mut fresh.1));
+                map.tuples.insert(id, new_id);
+                Type::Tuple(new_id)
+            },
+            Type::Array(id) => {
+                if let Some(new_id) = map.arrays.get(&id) {
+                    return Type::Array(*new_id);
+                }
+                let elem = self.preview_type(other, map, fresh, other.arrays[id as usize]);
+                let new_id = self.find_array(elem).unwrap_or_else(|| next(&mut fresh.2));
+                map.arrays.insert(id, new_id);
+                Type::Array(new_id)
+            },
+            t => map.ty(t),
+        }
+    }
+
+    /// interns a side table type of other into this registry
+    fn import_type(&mut self, other: &InMemoryRegistry<'code>, map: &mut IdMap, t: Type) -> Type {
+        match t {
+            Type::Function(id) => {
+                if let Some(new_id) = map.functions.get(&id) {
+                    return Type::Function(*new_id);
+                }
+                let sig = &other.functions[id as usize];
+                let params = sig.params.iter().map(|p| self.import_type(other, map, *p)).collect();
+                let ret = self.import_type(other, map, sig.ret);
+                let new_id = self.add_function(FnSig { params, ret });
+                map.functions.insert(id, new_id);
+                Type::Function(new_id)
+            },
+            Type::Tuple(id) => {
+                if let Some(new_id) = map.tuples.get(&id) {
+                    return Type::Tuple(*new_id);
+                }
+                let elems = other.tuples[id as usize].iter().map(|e| self.import_type(other, map, *e)).collect();
+                let new_id = self.add_tuple(elems);
+                map.tuples.insert(id, new_id);
+                Type::Tuple(new_id)
+            },
+            Type::Array(id) => {
+                if let Some(new_id) = map.arrays.get(&id) {
+                    return Type::Array(*new_id);
+                }
+                let elem = self.import_type(other, map, other.arrays[id as usize]);
+                let new_id = self.add_array(elem);
+                map.arrays.insert(id, new_id);
+                Type::Array(new_id)
+            },
+            t => map.ty(t),
+        }
+    }
+
+    /// rewrites every stored class and property id through the map
+    fn apply(&mut self, map: &IdMap) {
+        self.classes = self.classes.drain()
+            .map(|(id, (meta, name))| (map.class(id), (map.meta(&meta), name)))
+            .collect();
+        self.resolution_cache.clear();
+        self.children = self.children.drain()
+            .map(|(id, children)| (map.class(id), children.into_iter().map(|child| map.class(child)).collect()))
+            .collect();
+        self.properties = self.properties.drain()
+            .map(|(id, (prop, name))| (map.property_id(id), (map.property(prop), name)))
+            .collect();
+        for id in self.class_names.values_mut() {
+            *id = map.class(*id);
+        }
+        for by_class in self.property_names.values_mut() {
+            *by_class = by_class.drain().map(|(class, id)| (map.class(class), map.property_id(id))).collect();
+        }
+
+        // Side table entries move to their new slots before their contents are rewritten
+        fn permute<T>(items: Vec<T>, ids: &HashMap<u32, u32>) -> Vec<T> {
+            let mut placed: Vec<(u32, T)> = (0..).zip(items).map(|(id, item)| (ids.get(&id).copied().unwrap_or(id), item)).collect();
+            placed.sort_by_key(|(id, _)| *id);
+            placed.into_iter().map(|(_, item)| item).collect()
+        }
+        self.functions = permute(mem::take(&mut self.functions), &map.functions);
+        self.tuples = permute(mem::take(&mut self.tuples), &map.tuples);
+        self.arrays = permute(mem::take(&mut self.arrays), &map.arrays);
+
+        for sig in &mut self.functions {
+            sig.params.iter_mut().for_each(|t| *t = map.ty(*t));
+            sig.ret = map.ty(sig.ret);
+        }
+        self.function_ids = (0..).zip(&self.functions).map(|(id, sig)| (sig.clone(), id)).collect();
+        for elems in &mut self.tuples {
+            elems.iter_mut().for_each(|t| *t = map.ty(*t));
+        }
+        self.tuple_ids = (0..).zip(&self.tuples).map(|(id, elems)| (elems.clone(), id)).collect();
+        self.arrays.iter_mut().for_each(|t| *t = map.ty(*t));
+        self.array_ids = (0..).zip(&self.arrays).map(|(id, elem)| (*elem, id)).collect();
+
+        self.aliases = self.aliases.drain().map(|(id, t)| (map.class(id), map.ty(t))).collect();
+        self.attributes = self.attributes.drain().map(|(id, attrs)| (map.property_id(id), attrs)).collect();
+    }
+
+    /// reresolves the class and then everything that inherits from it, parents before children
+    pub fn reresolve_descendants(&mut self, id: ClassID) -> Result<(), ClassError> {
+        self.reresolve(id)?;
+
+        // a class always has more ancestors than any of its ancestors
+        let mut descendants: Vec<(usize, ClassID)> = self.classes.iter()
+            .filter(|(_, (meta, _))| meta.ancestors.contains(&id))
+            .map(|(child, (meta, _))| (meta.ancestors.len(), *child))
+            .collect();
+        descendants.sort();
+        for (_, child) in descendants {
+            self.reresolve(child)?;
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -460,17 +2744,17 @@ mod class_meta_tests {
         let a_id = setup_class(
             &mut registry,
             "A",
-            HashSet::new(),
+            vec![],
             vec![("a1", Type::Int), ("a2", Type::String)],
-        );
+        ).unwrap();
         
         // Create class B inheriting from A with property b1
         let b_id = setup_class(
             &mut registry,
             "B",
-            HashSet::from([a_id]),
+            vec![a_id],
             vec![("b1", Type::Float)],
-        );
+        ).unwrap();
         
         // Verify B's accessible properties include both its own and those from A
         let b_meta = registry.get_class(b_id).unwrap();
@@ -495,17 +2779,17 @@ mod class_meta_tests {
         let a_id = setup_class(
             &mut registry,
             "A",
-            HashSet::new(),
+            vec![],
             vec![("name", Type::String), ("age", Type::Int)],
-        );
+        ).unwrap();
         
         // Create class B inheriting from A with its own "name" property
         let b_id = setup_class(
             &mut registry,
             "B",
-            HashSet::from([a_id]),
+            vec![a_id],
             vec![("name", Type::String)], // Same name as A's property
-        );
+        ).unwrap();
         
         // Verify B's accessible properties include "name" and "age"
         let b_meta = registry.get_class(b_id).unwrap();
@@ -532,33 +2816,33 @@ mod class_meta_tests {
         let a_id = setup_class(
             &mut registry,
             "A",
-            HashSet::new(),
+            vec![],
             vec![("a_prop", Type::Int)],
-        );
+        ).unwrap();
         
         // Create class B inheriting from A with property "b_prop"
         let b_id = setup_class(
             &mut registry,
             "B",
-            HashSet::from([a_id]),
+            vec![a_id],
             vec![("b_prop", Type::Float)],
-        );
+        ).unwrap();
         
         // Create class C inheriting from A with property "c_prop"
         let c_id = setup_class(
             &mut registry,
             "C",
-            HashSet::from([a_id]),
+            vec![a_id],
             vec![("c_prop", Type::String)],
-        );
+        ).unwrap();
         
         // Create class D inheriting from both B and C
         let d_id = setup_class(
             &mut registry,
             "D",
-            HashSet::from([b_id, c_id]),
+            vec![b_id, c_id],
             vec![("d_prop", Type::Int)],
-        );
+        ).unwrap();
         
         // Verify D's accessible properties
         let d_meta = registry.get_class(d_id).unwrap();
@@ -591,33 +2875,33 @@ mod class_meta_tests {
         let a_id = setup_class(
             &mut registry,
             "A",
-            HashSet::new(),
+            vec![],
             vec![("common_prop", Type::Int)],
-        );
+        ).unwrap();
         
         // Create class B inheriting from A
         let b_id = setup_class(
             &mut registry,
             "B",
-            HashSet::from([a_id]),
+            vec![a_id],
             vec![("b_prop", Type::Float)],
-        );
+        ).unwrap();
         
         // Create class C inheriting from A
         let c_id = setup_class(
             &mut registry,
             "C",
-            HashSet::from([a_id]),
+            vec![a_id],
             vec![("c_prop", Type::String)],
-        );
+        ).unwrap();
         
         // Create class D inheriting from both B and C
         let d_id = setup_class(
             &mut registry,
             "D",
-            HashSet::from([b_id, c_id]),
+            vec![b_id, c_id],
             vec![("d_prop", Type::Int)],
-        );
+        ).unwrap();
         
         // Verify D's accessible properties
         let d_meta = registry.get_class(d_id).unwrap();
@@ -642,25 +2926,25 @@ mod class_meta_tests {
         let x_id = setup_class(
             &mut registry,
             "X",
-            HashSet::new(),
+            vec![],
             vec![("shared_name", Type::Int)],
-        );
+        ).unwrap();
         
         // Create class Y with property "shared_name" (different source, same name)
         let y_id = setup_class(
             &mut registry,
             "Y",
-            HashSet::new(),
+            vec![],
             vec![("shared_name", Type::Float)], // Note: Different type
-        );
+        ).unwrap();
         
         // Create class Z inheriting from both X and Y
         let z_id = setup_class(
             &mut registry,
             "Z",
-            HashSet::from([x_id, y_id]),
+            vec![x_id, y_id],
             vec![("z_prop", Type::String)],
-        );
+        ).unwrap();
         
         // Verify Z's properties
         let z_meta = registry.get_class(z_id).unwrap();
@@ -682,34 +2966,88 @@ mod class_meta_tests {
                "Z should not have shared_name in accessible properties due to clash");
     }
     
+    #[test]
+    fn test_parent_order_preserved() {
+        let mut registry = InMemoryRegistry::new();
+        let a_id = setup_class(&mut registry, "A", vec![], vec![]).unwrap();
+        let b_id = setup_class(&mut registry, "B", vec![], vec![]).unwrap();
+        let c_id = setup_class(&mut registry, "C", vec![], vec![]).unwrap();
+
+        // duplicates keep their first position
+        let d_id = setup_class(&mut registry, "D", vec![c_id, a_id, c_id, b_id], vec![]).unwrap();
+        let d_meta = registry.get_class(d_id).unwrap();
+        assert_eq!(d_meta.parents, vec![c_id, a_id, b_id]);
+        assert_eq!(d_meta.ancestors, HashSet::from([OBJECT_ID, a_id, b_id, c_id]));
+
+        let bytes = registry.serialize();
+        let reloaded = InMemoryRegistry::deserialize(&bytes).unwrap();
+        assert_eq!(reloaded.get_class(d_id).unwrap().parents, vec![c_id, a_id, b_id]);
+    }
+
+    #[test]
+    fn test_clash_policy() {
+        let mut registry = InMemoryRegistry::new();
+        let x_id = setup_class(&mut registry, "X", vec![], vec![("shared_name", Type::Int)]).unwrap();
+        let y_id = setup_class(&mut registry, "Y", vec![], vec![("shared_name", Type::Float)]).unwrap();
+        let z_id = registry.add_class_id("Z");
+
+        let pick = |parents: &[ClassID], policy| {
+            let meta = ClassMeta::new_with_policy(&registry, z_id, parents.to_vec(), HashMap::new(), policy).unwrap();
+            assert!(meta.clashing_properties.is_empty());
+            assert_eq!(meta.shadowed_properties["shared_name"].len(), 1);
+            meta.accessble_properties["shared_name"].source
+        };
+        assert_eq!(pick(&[x_id, y_id], ClashPolicy::FirstParent), x_id);
+        assert_eq!(pick(&[y_id, x_id], ClashPolicy::FirstParent), y_id);
+        assert_eq!(pick(&[x_id, y_id], ClashPolicy::LastParent), y_id);
+        assert_eq!(pick(&[y_id, x_id], ClashPolicy::LastParent), x_id);
+
+        assert_eq!(
+            ClassMeta::new_with_policy(&registry, z_id, vec![x_id, y_id], HashMap::new(), ClashPolicy::Error),
+            Err(ClassError::AmbiguousProperty("shared_name".to_string())),
+        );
+        assert!(ClassMeta::new_with_policy(&registry, z_id, vec![x_id], HashMap::new(), ClashPolicy::Error).is_ok());
+
+        // Keep matches plain new
+        assert_eq!(
+            ClassMeta::new_with_policy(&registry, z_id, vec![x_id, y_id], HashMap::new(), ClashPolicy::Keep).unwrap(),
+            ClassMeta::new(&registry, z_id, vec![x_id, y_id], HashMap::new()).unwrap(),
+        );
+    }
+
     #[test]
     fn test_shadow_resolving_clash() {
         // Test case where a class defines a property that shadows clashing inherited properties
         let mut registry = InMemoryRegistry::new();
+
+        // Types for the properties, the override has to be usable as both of the clashing ones
+        let t_id = setup_class(&mut registry, "T", vec![], vec![]).unwrap();
+        let u_id = setup_class(&mut registry, "U", vec![], vec![]).unwrap();
+        let v_id = setup_class(&mut registry, "V", vec![t_id, u_id], vec![]).unwrap();
         
         // Create class X with property "shared_name"
         let x_id = setup_class(
             &mut registry,
             "X",
-            HashSet::new(),
-            vec![("shared_name", Type::Int)],
-        );
+            vec![],
+            vec![("shared_name", Type::Class(t_id))],
+        ).unwrap();
         
         // Create class Y with property "shared_name" (different source, same name)
         let y_id = setup_class(
             &mut registry,
             "Y",
-            HashSet::new(),
-            vec![("shared_name", Type::Float)],
-        );
+            vec![],
+            vec![("shared_name", Type::Class(u_id))],
+        ).unwrap();
         
         // Create class Z inheriting from both X and Y (will have clash)
         let z_id = setup_class(
             &mut registry,
             "Z",
-            HashSet::from([x_id, y_id]),
+            vec![x_id, y_id],
             vec![],
-        );
+        ).unwrap();
         
         // Verify Z has clashing property
         let z_meta = registry.get_class(z_id).unwrap();
@@ -720,9 +3058,9 @@ mod class_meta_tests {
         let w_id = setup_class(
             &mut registry,
             "W",
-            HashSet::from([z_id]),
-            vec![("shared_name", Type::String)], // W defines its own shared_name
-        );
+            vec![z_id],
+            vec![("shared_name", Type::Class(v_id))], // W defines its own shared_name
+        ).unwrap();
         
         // Verify W's properties
         let w_meta = registry.get_class(w_id).unwrap();
@@ -744,6 +3082,71 @@ mod class_meta_tests {
         assert_eq!(accessible_prop.source, w_id, "The accessible shared_name should be W's own");
     }
     
+    #[test]
+    fn test_property_iteration_order() {
+        // Property maps iterate by name no matter the declaration order
+        let mut registry = InMemoryRegistry::new();
+
+        let a_id = setup_class(
+            &mut registry,
+            "A",
+            vec![],
+            vec![("zeta", Type::Int), ("alpha", Type::Int), ("mid", Type::Int)],
+        ).unwrap();
+
+        let b_id = setup_class(
+            &mut registry,
+            "B",
+            vec![a_id],
+            vec![("mid", Type::Int), ("beta", Type::Float), ("omega", Type::String)],
+        ).unwrap();
+
+        let b_meta = registry.get_class(b_id).unwrap();
+        let names: Vec<&str> = b_meta.accessble_properties.keys().copied().collect();
+        assert_eq!(names, vec!["alpha", "beta", "mid", "omega", "zeta"]);
+        let shadowed: Vec<&str> = b_meta.shadowed_properties.keys().copied().collect();
+        assert_eq!(shadowed, vec!["mid"]);
+    }
+
+    #[test]
+    fn test_shadowed_clash_warning() {
+        // Same setup as test_shadow_resolving_clash
+        let mut registry = InMemoryRegistry::new();
+
+        let t_id = setup_class(&mut registry, "T", vec![], vec![]).unwrap();
+        let u_id = setup_class(&mut registry, "U", vec![], vec![]).unwrap();
+        let v_id = setup_class(&mut registry, "V", vec![t_id, u_id], vec![]).unwrap();
+        let x_id = setup_class(&mut registry, "X", vec![], vec![("shared_name", Type::Class(t_id))]).unwrap();
+        let y_id = setup_class(&mut registry, "Y", vec![], vec![("shared_name", Type::Class(u_id))]).unwrap();
+        let z_id = setup_class(&mut registry, "Z", vec![x_id, y_id], vec![]).unwrap();
+
+        let w_id = registry.add_class_id("W");
+        let prop = create_property(&mut registry, "shared_name", w_id, Type::Class(v_id));
+        let mut warnings = Vec::new();
+        let w_meta = ClassMeta::new_with_warnings(
+            &registry,
+            w_id,
+            vec![z_id],
+            HashMap::from([("shared_name", prop)]),
+            &mut warnings,
+        ).unwrap();
+
+        // resolution is the same as without collecting warnings
+        assert_eq!(w_meta, ClassMeta::new(&registry, w_id, vec![z_id], HashMap::from([("shared_name", prop)])).unwrap());
+
+        let mut sources = vec![x_id, y_id];
+        sources.sort();
+        assert_eq!(warnings, vec![ClassWarning::ShadowedClash { name: "shared_name".to_string(), sources }]);
+
+        // shadowing a single property is not worth a warning
+        let a_id = setup_class(&mut registry, "A", vec![], vec![("name", Type::String)]).unwrap();
+        let b_id = registry.add_class_id("B");
+        let prop = create_property(&mut registry, "name", b_id, Type::String);
+        let mut warnings = Vec::new();
+        ClassMeta::new_with_warnings(&registry, b_id, vec![a_id], HashMap::from([("name", prop)]), &mut warnings).unwrap();
+        assert!(warnings.is_empty());
+    }
+
     #[test]
     fn test_multi_level_inheritance() {
         // Test multi-level inheritance (5 levels)
@@ -754,37 +3157,37 @@ mod class_meta_tests {
         let a_id = setup_class(
             &mut registry,
             "A",
-            HashSet::new(),
+            vec![],
             vec![("a_prop", Type::Int), ("common", Type::Int)],
-        );
+        ).unwrap();
         
         let b_id = setup_class(
             &mut registry,
             "B",
-            HashSet::from([a_id]),
+            vec![a_id],
             vec![("b_prop", Type::Float)],
-        );
+        ).unwrap();
         
         let c_id = setup_class(
             &mut registry,
             "C",
-            HashSet::from([b_id]),
-            vec![("c_prop", Type::String), ("common", Type::String)], // Shadows A's common
-        );
+            vec![b_id],
+            vec![("c_prop", Type::String), ("common", Type::Int)], // Shadows A's common
+        ).unwrap();
         
         let d_id = setup_class(
             &mut registry,
             "D",
-            HashSet::from([c_id]),
+            vec![c_id],
             vec![("d_prop", Type::Int)],
-        );
+        ).unwrap();
         
         let e_id = setup_class(
             &mut registry,
             "E",
-            HashSet::from([d_id]),
+            vec![d_id],
             vec![("e_prop", Type::Float)],
-        );
+        ).unwrap();
         
         // Verify E's properties
         let e_meta = registry.get_class(e_id).unwrap();
@@ -831,44 +3234,44 @@ mod class_meta_tests {
         let a_id = setup_class(
             &mut registry,
             "A",
-            HashSet::new(),
+            vec![],
             vec![("prop1", Type::Int)],
-        );
+        ).unwrap();
         
         let b_id = setup_class(
             &mut registry,
             "B",
-            HashSet::from([a_id]),
+            vec![a_id],
             vec![],
-        );
+        ).unwrap();
         
         let c_id = setup_class(
             &mut registry,
             "C",
-            HashSet::from([a_id]),
-            vec![("prop1", Type::Float), ("prop2", Type::String)], // C shadows A's prop1
-        );
+            vec![a_id],
+            vec![("prop1", Type::Int), ("prop2", Type::String)], // C shadows A's prop1
+        ).unwrap();
         
         let d_id = setup_class(
             &mut registry,
             "D",
-            HashSet::from([b_id]),
+            vec![b_id],
             vec![],
-        );
+        ).unwrap();
         
         let e_id = setup_class(
             &mut registry,
             "E",
-            HashSet::from([b_id, c_id]),
-            vec![("prop2", Type::Int)], // E shadows C's prop2
-        );
+            vec![b_id, c_id],
+            vec![("prop2", Type::String)], // E shadows C's prop2
+        ).unwrap();
         
         let f_id = setup_class(
             &mut registry,
             "F",
-            HashSet::from([d_id, e_id]),
+            vec![d_id, e_id],
             vec![("prop3", Type::Float)],
-        );
+        ).unwrap();
         
         // Verify F's properties
         let f_meta = registry.get_class(f_id).unwrap();
@@ -894,4 +3297,1798 @@ mod class_meta_tests {
         // F should have its own prop3
         assert!(f_meta.accessble_properties.contains_key("prop3"), "F should have its own prop3");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_unknown_parent() {
+        // Referencing a parent that was never registered should error instead of panicking
+        let mut registry = InMemoryRegistry::new();
+
+        let a_id = setup_class(
+            &mut registry,
+            "A",
+            vec![],
+            vec![("a_prop", Type::Int)],
+        ).unwrap();
+
+        let bogus = a_id + 42;
+        let result = setup_class(
+            &mut registry,
+            "B",
+            vec![a_id, bogus],
+            vec![],
+        );
+        assert_eq!(result, Err(ClassError::UnknownParent(bogus)));
+
+        // Nothing should have been stored for the failed class
+        let b_id = registry.get_class_id("B").unwrap();
+        assert!(registry.get_class(b_id).is_none(), "B should not have been added");
+    }
+
+    #[test]
+    fn test_compatible_override() {
+        // A child may narrow a class typed property to a subclass
+        let mut registry = InMemoryRegistry::new();
+
+        let animal_id = setup_class(&mut registry, "Animal", vec![], vec![]).unwrap();
+        let dog_id = setup_class(&mut registry, "Dog", vec![animal_id], vec![]).unwrap();
+
+        let owner_id = setup_class(
+            &mut registry,
+            "Owner",
+            vec![],
+            vec![("pet", Type::Class(animal_id))],
+        ).unwrap();
+
+        let dog_owner_id = setup_class(
+            &mut registry,
+            "DogOwner",
+            vec![owner_id],
+            vec![("pet", Type::Class(dog_id))],
+        ).unwrap();
+
+        let meta = registry.get_class(dog_owner_id).unwrap();
+        assert_eq!(meta.accessble_properties["pet"].inner_type, Type::Class(dog_id));
+        assert!(meta.shadowed_properties.contains_key("pet"));
+    }
+
+    #[test]
+    fn test_widening_override() {
+        // Going from a subclass back up to its base is an error
+        let mut registry = InMemoryRegistry::new();
+
+        let animal_id = setup_class(&mut registry, "Animal", vec![], vec![]).unwrap();
+        let dog_id = setup_class(&mut registry, "Dog", vec![animal_id], vec![]).unwrap();
+
+        let owner_id = setup_class(
+            &mut registry,
+            "DogOwner",
+            vec![],
+            vec![("pet", Type::Class(dog_id))],
+        ).unwrap();
+
+        let result = setup_class(
+            &mut registry,
+            "AnyOwner",
+            vec![owner_id],
+            vec![("pet", Type::Class(animal_id))],
+        );
+        assert_eq!(result, Err(ClassError::IncompatibleOverride {
+            name: "pet".to_string(),
+            parent_type: Type::Class(dog_id),
+            child_type: Type::Class(animal_id),
+        }));
+    }
+
+    #[test]
+    fn test_unrelated_override() {
+        // Two classes with no inheritance relation can't replace each other
+        let mut registry = InMemoryRegistry::new();
+
+        let animal_id = setup_class(&mut registry, "Animal", vec![], vec![]).unwrap();
+        let rock_id = setup_class(&mut registry, "Rock", vec![], vec![]).unwrap();
+
+        let owner_id = setup_class(
+            &mut registry,
+            "Owner",
+            vec![],
+            vec![("pet", Type::Class(animal_id))],
+        ).unwrap();
+
+        let result = setup_class(
+            &mut registry,
+            "RockOwner",
+            vec![owner_id],
+            vec![("pet", Type::Class(rock_id))],
+        );
+        assert_eq!(result, Err(ClassError::IncompatibleOverride {
+            name: "pet".to_string(),
+            parent_type: Type::Class(animal_id),
+            child_type: Type::Class(rock_id),
+        }));
+
+        // and a primitive can't stand in for a class either
+        let result = setup_class(
+            &mut registry,
+            "IntOwner",
+            vec![owner_id],
+            vec![("pet", Type::Int)],
+        );
+        assert!(matches!(result, Err(ClassError::IncompatibleOverride { .. })));
+    }
+
+    #[test]
+    fn test_incompatible_override() {
+        // Redeclaring a string property as an int is an error
+        let mut registry = InMemoryRegistry::new();
+
+        let a_id = setup_class(
+            &mut registry,
+            "A",
+            vec![],
+            vec![("name", Type::String)],
+        ).unwrap();
+
+        let result = setup_class(
+            &mut registry,
+            "B",
+            vec![a_id],
+            vec![("name", Type::Int)],
+        );
+        assert_eq!(result, Err(ClassError::IncompatibleOverride {
+            name: "name".to_string(),
+            parent_type: Type::String,
+            child_type: Type::Int,
+        }));
+
+        // The failed attempt left no properties behind so a corrected retry goes through
+        let b_id = registry.get_class_id("B").unwrap();
+        assert_eq!(registry.get_property_id("name", b_id), None);
+        let retry = setup_class(
+            &mut registry,
+            "B",
+            vec![a_id],
+            vec![("name", Type::String)],
+        ).unwrap();
+        assert_eq!(retry, b_id);
+        assert_eq!(registry.get_class(b_id).unwrap().accessble_properties["name"].source, b_id);
+    }
+
+    #[test]
+    fn test_direct_cycle() {
+        // A -> B and then trying to make B a parent of A
+        let mut registry = InMemoryRegistry::new();
+
+        let a_id = setup_class(
+            &mut registry,
+            "A",
+            vec![],
+            vec![("a_prop", Type::Int)],
+        ).unwrap();
+
+        let b_id = setup_class(
+            &mut registry,
+            "B",
+            vec![a_id],
+            vec![],
+        ).unwrap();
+
+        assert!(registry.would_create_cycle(a_id, &[b_id]));
+        assert!(registry.would_create_cycle(a_id, &[a_id]), "a class can't be its own parent");
+        assert!(!registry.would_create_cycle(b_id, &[a_id]));
+
+        let result = ClassMeta::new(&registry, a_id, vec![b_id], HashMap::new());
+        assert_eq!(result, Err(ClassError::CyclicInheritance));
+    }
+
+    #[test]
+    fn test_indirect_cycle() {
+        // A -> B -> C and then trying to make C a parent of A
+        let mut registry = InMemoryRegistry::new();
+
+        let a_id = setup_class(
+            &mut registry,
+            "A",
+            vec![],
+            vec![],
+        ).unwrap();
+
+        let b_id = setup_class(
+            &mut registry,
+            "B",
+            vec![a_id],
+            vec![],
+        ).unwrap();
+
+        let c_id = setup_class(
+            &mut registry,
+            "C",
+            vec![b_id],
+            vec![],
+        ).unwrap();
+
+        assert!(registry.would_create_cycle(a_id, &[c_id]));
+        assert!(registry.would_create_cycle(b_id, &[c_id]));
+
+        let result = ClassMeta::new(&registry, a_id, vec![c_id], HashMap::new());
+        assert_eq!(result, Err(ClassError::CyclicInheritance));
+    }
+
+    #[test]
+    fn test_clash_from_later_parent() {
+        // However P and Z are ordered, x has to end up only clashing
+        //   X (x)  Y (x)
+        //      \  /
+        //  P (x) Z
+        //     \ /
+        //      C
+        let mut registry = InMemoryRegistry::new();
+
+        let x_id = setup_class(&mut registry, "X", vec![], vec![("x", Type::Int)]).unwrap();
+        let y_id = setup_class(&mut registry, "Y", vec![], vec![("x", Type::Int)]).unwrap();
+        let z_id = setup_class(&mut registry, "Z", vec![x_id, y_id], vec![]).unwrap();
+        let p_id = setup_class(&mut registry, "P", vec![], vec![("x", Type::Int)]).unwrap();
+        let c_id = setup_class(&mut registry, "C", vec![p_id, z_id], vec![]).unwrap();
+
+        // The clash takes over P's copy instead of leaving x both accessible and clashing
+        let c_meta = registry.get_class(c_id).unwrap();
+        assert!(!c_meta.accessble_properties.contains_key("x"), "x should not stay accessible");
+        let sources: HashSet<ClassID> = c_meta.clashing_properties["x"].iter().map(|p| p.source).collect();
+        assert_eq!(sources, HashSet::from([x_id, y_id, p_id]));
+    }
+}
+
+#[cfg(test)]
+mod registry_tests {
+    use super::*;
+
+    /// A -> B -> C without any properties
+    fn chain() -> (InMemoryRegistry<'static>, ClassID, ClassID, ClassID) {
+        let mut registry = InMemoryRegistry::new();
+        let a_id = setup_class(&mut registry, "A", vec![], vec![]).unwrap();
+        let b_id = setup_class(&mut registry, "B", vec![a_id], vec![]).unwrap();
+        let c_id = setup_class(&mut registry, "C", vec![b_id], vec![]).unwrap();
+        (registry, a_id, b_id, c_id)
+    }
+
+    /// A -> B -> D
+    ///  \-> C -/
+    fn diamond() -> (InMemoryRegistry<'static>, ClassID, ClassID, ClassID, ClassID) {
+        let mut registry = InMemoryRegistry::new();
+        let a_id = setup_class(&mut registry, "A", vec![], vec![]).unwrap();
+        let b_id = setup_class(&mut registry, "B", vec![a_id], vec![]).unwrap();
+        let c_id = setup_class(&mut registry, "C", vec![a_id], vec![]).unwrap();
+        let d_id = setup_class(&mut registry, "D", vec![b_id, c_id], vec![]).unwrap();
+        (registry, a_id, b_id, c_id, d_id)
+    }
+
+    /// Dog inherits from Animal, neither has properties
+    fn animal_dog() -> (InMemoryRegistry<'static>, ClassID, ClassID) {
+        let mut registry = InMemoryRegistry::new();
+        let animal_id = setup_class(&mut registry, "Animal", vec![], vec![]).unwrap();
+        let dog_id = setup_class(&mut registry, "Dog", vec![animal_id], vec![]).unwrap();
+        (registry, animal_id, dog_id)
+    }
+
+    #[test]
+    fn test_is_subclass_of() {
+        // A -> B -> C and an unrelated X
+        let (mut registry, a_id, b_id, c_id) = chain();
+        let x_id = setup_class(&mut registry, "X", vec![], vec![]).unwrap();
+
+        // reflexive
+        assert!(registry.is_subclass_of(a_id, a_id));
+        assert!(registry.is_subclass_of(c_id, c_id));
+
+        // direct and transitive
+        assert!(registry.is_subclass_of(b_id, a_id));
+        assert!(registry.is_subclass_of(c_id, b_id));
+        assert!(registry.is_subclass_of(c_id, a_id));
+
+        // not the other way around
+        assert!(!registry.is_subclass_of(a_id, b_id));
+        assert!(!registry.is_subclass_of(a_id, c_id));
+
+        // unrelated
+        assert!(!registry.is_subclass_of(x_id, a_id));
+        assert!(!registry.is_subclass_of(c_id, x_id));
+    }
+
+    #[test]
+    fn test_is_assignable_to() {
+        let (registry, a_id, b_id, _) = chain();
+
+        // identical primitives
+        assert!(Type::Int.is_assignable_to(Type::Int, &registry));
+        assert!(Type::Float.is_assignable_to(Type::Float, &registry));
+        assert!(Type::String.is_assignable_to(Type::String, &registry));
+        assert!(!Type::Int.is_assignable_to(Type::Float, &registry));
+        assert!(!Type::String.is_assignable_to(Type::Int, &registry));
+
+        // classes follow inheritance
+        assert!(Type::Class(b_id).is_assignable_to(Type::Class(a_id), &registry));
+        assert!(Type::Class(b_id).is_assignable_to(Type::Class(b_id), &registry));
+        assert!(!Type::Class(a_id).is_assignable_to(Type::Class(b_id), &registry));
+
+        // primitives and classes never mix
+        assert!(!Type::Int.is_assignable_to(Type::Class(a_id), &registry));
+        assert!(!Type::Class(a_id).is_assignable_to(Type::Int, &registry));
+
+        // invalid is assignable to nothing
+        assert!(!Type::Invalid.is_assignable_to(Type::Invalid, &registry));
+        assert!(!Type::Invalid.is_assignable_to(Type::Int, &registry));
+        assert!(!Type::Int.is_assignable_to(Type::Invalid, &registry));
+    }
+
+    #[test]
+    fn test_common_ancestor_chain() {
+        let (registry, a_id, b_id, c_id) = chain();
+
+        assert_eq!(registry.common_ancestor(c_id, b_id), Some(b_id));
+        assert_eq!(registry.common_ancestor(b_id, c_id), Some(b_id));
+        assert_eq!(registry.common_ancestor(c_id, a_id), Some(a_id));
+        assert_eq!(registry.common_ancestor(c_id, c_id), Some(c_id));
+    }
+
+    #[test]
+    fn test_common_ancestor_diamond() {
+        let (registry, a_id, b_id, c_id, d_id) = diamond();
+
+        assert_eq!(registry.common_ancestor(b_id, c_id), Some(a_id));
+        assert_eq!(registry.common_ancestor(d_id, b_id), Some(b_id));
+        assert_eq!(registry.common_ancestor(d_id, c_id), Some(c_id));
+    }
+
+    #[test]
+    fn test_common_ancestor_ambiguous() {
+        // X and Y are both parents of P and Q so neither is more specific
+        let mut registry = InMemoryRegistry::new();
+
+        let x_id = setup_class(&mut registry, "X", vec![], vec![]).unwrap();
+        let y_id = setup_class(&mut registry, "Y", vec![], vec![]).unwrap();
+        let p_id = setup_class(&mut registry, "P", vec![x_id, y_id], vec![]).unwrap();
+        let q_id = setup_class(&mut registry, "Q", vec![x_id, y_id], vec![]).unwrap();
+        let z_id = setup_class(&mut registry, "Z", vec![], vec![]).unwrap();
+
+        assert_eq!(registry.common_ancestor(p_id, q_id), None);
+
+        // unrelated classes only share the root
+        assert_eq!(registry.common_ancestor(p_id, z_id), Some(OBJECT_ID));
+    }
+
+    #[test]
+    fn test_object_root() {
+        let mut registry = InMemoryRegistry::new();
+
+        // even without any children Object stays
+        assert_eq!(registry.remove_class(OBJECT_ID), Err(ClassError::RemoveObject));
+
+        assert_eq!(registry.get_class_id(OBJECT_NAME), Some(OBJECT_ID));
+        assert_eq!(registry.get_type(OBJECT_NAME), Some(Type::Class(OBJECT_ID)));
+        let object = registry.get_class(OBJECT_ID).unwrap();
+        assert!(object.parents.is_empty());
+        assert!(object.ancestors.is_empty());
+
+        let a_id = setup_class(&mut registry, "A", vec![], vec![]).unwrap();
+        let b_id = setup_class(&mut registry, "B", vec![a_id], vec![]).unwrap();
+        let x_id = setup_class(&mut registry, "X", vec![], vec![]).unwrap();
+        assert_ne!(a_id, OBJECT_ID);
+
+        // roots get Object as their parent, everything has it as an ancestor
+        assert_eq!(registry.get_class(a_id).unwrap().parents, vec![OBJECT_ID]);
+        assert_eq!(registry.get_class(b_id).unwrap().parents, vec![a_id]);
+        for id in [a_id, b_id, x_id] {
+            assert!(registry.get_class(id).unwrap().ancestors.contains(&OBJECT_ID));
+            assert!(registry.is_subclass_of(id, OBJECT_ID));
+        }
+
+        assert_eq!(registry.common_ancestor(b_id, x_id), Some(OBJECT_ID));
+        assert_eq!(registry.remove_class(OBJECT_ID), Err(ClassError::RemoveObject));
+    }
+
+    #[test]
+    fn test_remove_class_and_property() {
+        let mut registry = InMemoryRegistry::new();
+
+        let a_id = setup_class(&mut registry, "A", vec![], vec![("a1", Type::Int)]).unwrap();
+        let b_id = setup_class(&mut registry, "B", vec![a_id], vec![("b1", Type::Float), ("b2", Type::String)]).unwrap();
+
+        // removing a single property
+        let b2_id = registry.get_property_id("b2", b_id).unwrap();
+        let removed = registry.remove_property(b2_id).unwrap();
+        assert_eq!(removed.id, b2_id);
+        assert!(registry.get_property(b2_id).is_none());
+        assert!(registry.get_property_id("b2", b_id).is_none());
+        assert!(registry.remove_property(b2_id).is_none(), "already removed");
+
+        // removing a leaf class takes its name and own properties with it
+        let b1_id = registry.get_property_id("b1", b_id).unwrap();
+        let meta = registry.remove_class(b_id).unwrap().unwrap();
+        assert!(meta.parents.contains(&a_id));
+        assert!(registry.get_class(b_id).is_none());
+        assert!(registry.get_class_id("B").is_none());
+        assert!(registry.get_property(b1_id).is_none());
+        assert!(registry.get_property_id("b1", b_id).is_none());
+
+        // A is untouched
+        assert!(registry.get_property_id("a1", a_id).is_some());
+
+        // now that B is gone A can be removed as well
+        assert!(registry.remove_class(a_id).unwrap().is_some());
+        assert!(registry.get_class_id("A").is_none());
+        assert_eq!(registry.remove_class(a_id), Ok(None));
+    }
+
+    #[test]
+    fn test_remove_referenced_class() {
+        let mut registry = InMemoryRegistry::new();
+
+        let a_id = setup_class(&mut registry, "A", vec![], vec![("a1", Type::Int)]).unwrap();
+        let b_id = setup_class(&mut registry, "B", vec![a_id], vec![]).unwrap();
+
+        assert_eq!(registry.remove_class(a_id), Err(ClassError::StillParentOf(b_id)));
+
+        // nothing was removed
+        assert!(registry.get_class(a_id).is_some());
+        assert_eq!(registry.get_class_id("A"), Some(a_id));
+        assert!(registry.get_property_id("a1", a_id).is_some());
+    }
+
+    #[test]
+    fn test_serialize_round_trip() {
+        // A -> B -> D
+        //  \-> C -/
+        let mut registry = InMemoryRegistry::new();
+
+        let a_id = setup_class(&mut registry, "A", vec![], vec![("name", Type::String), ("a1", Type::Int)]).unwrap();
+        let b_id = setup_class(&mut registry, "B", vec![a_id], vec![("name", Type::String), ("shared", Type::Int)]).unwrap();
+        let c_id = setup_class(&mut registry, "C", vec![a_id], vec![("shared", Type::Float), ("link", Type::Class(a_id))]).unwrap();
+        let d_id = setup_class(&mut registry, "D", vec![b_id, c_id], vec![("d1", Type::Float)]).unwrap();
+
+        // a reserved name without a definition should survive too
+        let e_id = registry.add_class_id("E");
+
+        let bytes = registry.serialize();
+        let reloaded = InMemoryRegistry::deserialize(&bytes).unwrap();
+
+        assert_eq!(reloaded.get_cur_class_id(), registry.get_cur_class_id());
+        assert_eq!(reloaded.get_cur_property_id(), registry.get_cur_property_id());
+        assert_eq!(reloaded.get_class_id("E"), Some(e_id));
+        assert!(reloaded.get_class(e_id).is_none());
+
+        for id in [a_id, b_id, c_id, d_id] {
+            let (meta, name) = registry.get_class_and_name(id).unwrap();
+            let (new_meta, new_name) = reloaded.get_class_and_name(id).unwrap();
+            assert_eq!(name, new_name);
+            assert_eq!(meta, new_meta, "class {name} should reload identically");
+        }
+
+        for id in 0..registry.get_cur_property_id() {
+            assert_eq!(registry.get_property_and_name(id), reloaded.get_property_and_name(id));
+        }
+
+        // serializing is deterministic
+        assert_eq!(reloaded.serialize(), bytes);
+    }
+
+    #[test]
+    fn test_deserialize_errors() {
+        assert_eq!(InMemoryRegistry::deserialize(b"nope").unwrap_err(), DeserializeError::BadMagic);
+
+        let mut registry = InMemoryRegistry::new();
+        setup_class(&mut registry, "A", vec![], vec![("a1", Type::Int)]).unwrap();
+        let bytes = registry.serialize();
+        assert_eq!(InMemoryRegistry::deserialize(&bytes[..bytes.len() - 1]).unwrap_err(), DeserializeError::UnexpectedEnd);
+    }
+
+    /// builds a registry buffer by hand so it can hold records serialize never writes.
+    /// every class but Object derives from Object and every property is an int declared on its class
+    struct Buffer<'a>{
+        next_class_id: ClassID,
+        next_property_id: PropertyID,
+        functions: &'a [FnSig],
+        tuples: &'a [&'a [Type]],
+        arrays: &'a [Type],
+        classes: &'a [(ClassID, &'a str)],
+        properties: &'a [(PropertyID, ClassID, &'a str)],
+    }
+
+    impl Buffer<'_> {
+        fn bytes(&self) -> Vec<u8> {
+            let types = |out: &mut Vec<u8>, types: &[Type]| {
+                out.extend((types.len() as u32).to_le_bytes());
+                for t in types {
+                    out.extend(u64::from(*t).to_le_bytes());
+                }
+            };
+
+            let mut out = Vec::new();
+            out.extend(REGISTRY_MAGIC);
+            out.extend(self.next_class_id.to_le_bytes());
+            out.extend(self.next_property_id.to_le_bytes());
+            out.extend((self.functions.len() as u32).to_le_bytes());
+            for sig in self.functions {
+                types(&mut out, &sig.params);
+                out.extend(u64::from(sig.ret).to_le_bytes());
+            }
+            out.extend((self.tuples.len() as u32).to_le_bytes());
+            for elems in self.tuples {
+                types(&mut out, elems);
+            }
+            types(&mut out, self.arrays);
+            // no aliases
+            out.extend(0u32.to_le_bytes());
+
+            out.extend((self.classes.len() as u32).to_le_bytes());
+            for (id, name) in self.classes {
+                out.extend(id.to_le_bytes());
+                write_name(&mut out, name);
+                out.extend([1, 0]);
+                let parents: &[ClassID] = if *id == OBJECT_ID { &[] } else { &[OBJECT_ID] };
+                out.extend((parents.len() as u32).to_le_bytes());
+                for p in parents {
+                    out.extend(p.to_le_bytes());
+                }
+            }
+
+            out.extend((self.properties.len() as u32).to_le_bytes());
+            for (id, class, name) in self.properties {
+                out.extend(id.to_le_bytes());
+                out.extend(class.to_le_bytes());
+                write_name(&mut out, name);
+                out.extend(u64::from(Type::Int).to_le_bytes());
+                out.extend(class.to_le_bytes());
+                out.push(0);
+            }
+
+            // no attributes
+            out.extend(0u32.to_le_bytes());
+            out
+        }
+    }
+
+    const BASE: Buffer<'static> = Buffer {
+        next_class_id: 2,
+        next_property_id: 2,
+        functions: &[],
+        tuples: &[],
+        arrays: &[],
+        classes: &[(OBJECT_ID, "Object"), (1, "A")],
+        properties: &[(0, 1, "a1")],
+    };
+
+    #[test]
+    fn test_deserialize_hand_built() {
+        // the baseline buffer is fine
+        let bytes = BASE.bytes();
+        let reg = InMemoryRegistry::deserialize(&bytes).unwrap();
+        assert_eq!(reg.get_class(1).unwrap().accessble_properties["a1"].id, 0);
+    }
+
+    #[test]
+    fn test_deserialize_duplicate_records() {
+        let load = |buffer: Buffer| InMemoryRegistry::deserialize(&buffer.bytes()).map(|_| ()).unwrap_err();
+
+        // the same id stored under two names
+        assert_eq!(load(Buffer { classes: &[(OBJECT_ID, "Object"), (1, "A"), (1, "B")], ..BASE }), DeserializeError::DuplicateClass(1));
+
+        // the same name stored under two ids
+        assert_eq!(
+            load(Buffer { next_class_id: 3, classes: &[(OBJECT_ID, "Object"), (1, "A"), (2, "A")], ..BASE }),
+            DeserializeError::DuplicateName("A".to_string())
+        );
+
+        // a repeated property id, and a second a1 on A under a new id
+        assert_eq!(load(Buffer { properties: &[(0, 1, "a1"), (0, 1, "a2")], ..BASE }), DeserializeError::DuplicateProperty(0));
+        assert_eq!(load(Buffer { properties: &[(0, 1, "a1"), (1, 1, "a1")], ..BASE }), DeserializeError::DuplicateProperty(1));
+
+        // a repeated signature would otherwise be interned into one id
+        let sig = FnSig { params: vec![Type::Int], ret: Type::Float };
+        assert_eq!(load(Buffer { functions: &[sig.clone(), sig], ..BASE }), DeserializeError::DuplicateType);
+    }
+
+    #[test]
+    fn test_deserialize_bad_ids() {
+        let load = |buffer: Buffer| InMemoryRegistry::deserialize(&buffer.bytes()).map(|_| ()).unwrap_err();
+
+        // ids the counters never handed out
+        assert_eq!(load(Buffer { next_class_id: 1, ..BASE }), DeserializeError::IdOutOfRange(1));
+        assert_eq!(load(Buffer { next_property_id: 0, ..BASE }), DeserializeError::IdOutOfRange(0));
+
+        // a missing table entry or class
+        let bad = Type::Tuple(5);
+        assert_eq!(load(Buffer { arrays: &[bad], ..BASE }), DeserializeError::InvalidTypeReference(u64::from(bad)));
+        let bad = Type::Class(9);
+        assert_eq!(load(Buffer { tuples: &[&[bad]], ..BASE }), DeserializeError::InvalidTypeReference(u64::from(bad)));
+
+        // a tuple holding itself, and an array and tuple holding each other
+        let own = Type::Tuple(0);
+        assert_eq!(load(Buffer { tuples: &[&[Type::Int, own]], ..BASE }), DeserializeError::InvalidTypeReference(u64::from(own)));
+        assert!(matches!(
+            load(Buffer { tuples: &[&[Type::Array(0)]], arrays: &[Type::Tuple(0)], ..BASE }),
+            DeserializeError::InvalidTypeReference(_)
+        ));
+
+        // parts that point at other tables are fine as long as they end somewhere
+        let fine = Buffer { functions: &[FnSig { params: vec![Type::Array(0)], ret: Type::Tuple(0) }], tuples: &[&[Type::Array(0)]], arrays: &[Type::Class(1)], ..BASE };
+        let bytes = fine.bytes();
+        let reg = InMemoryRegistry::deserialize(&bytes).unwrap();
+        assert_eq!(reg.type_size(Type::Tuple(0)), mem::size_of::<usize>());
+    }
+
+    #[test]
+    fn test_resolve_property() {
+        // X and Y both declare shared_name, Z inherits both (clash), W shadows them
+        let mut registry = InMemoryRegistry::new();
+
+        let t_id = setup_class(&mut registry, "T", vec![], vec![]).unwrap();
+        let u_id = setup_class(&mut registry, "U", vec![], vec![]).unwrap();
+        let v_id = setup_class(&mut registry, "V", vec![t_id, u_id], vec![]).unwrap();
+
+        let x_id = setup_class(&mut registry, "X", vec![], vec![("shared_name", Type::Class(t_id)), ("x_prop", Type::Int)]).unwrap();
+        let y_id = setup_class(&mut registry, "Y", vec![], vec![("shared_name", Type::Class(u_id))]).unwrap();
+        let z_id = setup_class(&mut registry, "Z", vec![x_id, y_id], vec![]).unwrap();
+        let w_id = setup_class(&mut registry, "W", vec![z_id], vec![("shared_name", Type::Class(v_id))]).unwrap();
+
+        // clashing
+        let from_x = registry.resolve_property(z_id, "shared_name", x_id).unwrap();
+        assert_eq!(from_x.source, x_id);
+        assert_eq!(from_x.inner_type, Type::Class(t_id));
+        let from_y = registry.resolve_property(z_id, "shared_name", y_id).unwrap();
+        assert_eq!(from_y.source, y_id);
+        assert_eq!(from_y.inner_type, Type::Class(u_id));
+
+        // non clashing
+        let x_prop = registry.resolve_property(z_id, "x_prop", x_id).unwrap();
+        assert_eq!(x_prop.source, x_id);
+        assert!(registry.resolve_property(z_id, "x_prop", y_id).is_none());
+
+        // shadowed and the shadowing one
+        assert_eq!(registry.resolve_property(w_id, "shared_name", x_id), Some(from_x));
+        assert_eq!(registry.resolve_property(w_id, "shared_name", y_id), Some(from_y));
+        assert_eq!(registry.resolve_property(w_id, "shared_name", w_id).unwrap().inner_type, Type::Class(v_id));
+
+        // missing
+        assert!(registry.resolve_property(z_id, "shared_name", z_id).is_none());
+        assert!(registry.resolve_property(z_id, "nope", x_id).is_none());
+    }
+
+    #[test]
+    fn test_property_default() {
+        let mut registry = InMemoryRegistry::new();
+
+        let a_id = registry.add_class_id("A");
+        let wealth_id = registry.add_property_id("wealth", a_id);
+        let wealth = Property { id: wealth_id, inner_type: Type::Float, source: a_id, default: None };
+        registry.add_property_with_default(wealth_id, wealth, DefaultValue::Float(1.5)).unwrap();
+
+        let name_id = registry.add_property_id("name", a_id);
+        let name = Property { id: name_id, inner_type: Type::String, source: a_id, default: None };
+        registry.add_property_with_default(name_id, name, DefaultValue::String("nobody")).unwrap();
+
+        let wealth = *registry.get_property(wealth_id).unwrap();
+        let name = *registry.get_property(name_id).unwrap();
+        assert_eq!(wealth.default, Some(DefaultValue::Float(1.5)));
+        assert_eq!(name.default, Some(DefaultValue::String("nobody")));
+
+        let meta = ClassMeta::new(&registry, a_id, vec![], HashMap::from([("wealth", wealth), ("name", name)])).unwrap();
+        registry.add_class(a_id, meta).unwrap();
+
+        // children see the same defaults
+        let b_id = setup_class(&mut registry, "B", vec![a_id], vec![]).unwrap();
+        let b_meta = registry.get_class(b_id).unwrap();
+        assert_eq!(b_meta.accessble_properties["wealth"].default, Some(DefaultValue::Float(1.5)));
+        assert_eq!(b_meta.accessble_properties["name"].default, Some(DefaultValue::String("nobody")));
+
+        // and they survive serialization
+        let bytes = registry.serialize();
+        let reloaded = InMemoryRegistry::deserialize(&bytes).unwrap();
+        assert_eq!(reloaded.get_property(name_id).unwrap().default, Some(DefaultValue::String("nobody")));
+        assert_eq!(reloaded.get_class(b_id), registry.get_class(b_id));
+    }
+
+    #[test]
+    fn test_mismatched_default() {
+        let mut registry = InMemoryRegistry::new();
+
+        let a_id = registry.add_class_id("A");
+        let prop_id = registry.add_property_id("count", a_id);
+        let prop = Property { id: prop_id, inner_type: Type::Int, source: a_id, default: None };
+
+        assert_eq!(
+            registry.add_property_with_default(prop_id, prop, DefaultValue::String("three")),
+            Err(PropertyError::MismatchedDefault { expected: Type::Int, found: Type::String })
+        );
+
+        // the property is still unset so it can be added properly
+        assert!(!registry.get_property(prop_id).unwrap().inner_type.is_valid());
+        registry.add_property_with_default(prop_id, prop, DefaultValue::Int(3)).unwrap();
+        assert_eq!(registry.get_property(prop_id).unwrap().default, Some(DefaultValue::Int(3)));
+
+        // a second definition is a duplicate
+        assert_eq!(
+            registry.add_property_with_default(prop_id, prop, DefaultValue::Int(4)),
+            Err(PropertyError::Duplicate)
+        );
+    }
+
+    #[test]
+    fn test_function_signatures() {
+        let (mut registry, animal_id, dog_id) = animal_dog();
+
+        let f = registry.add_function(FnSig { params: vec![Type::Int], ret: Type::Class(animal_id) });
+        let same = registry.add_function(FnSig { params: vec![Type::Int], ret: Type::Class(animal_id) });
+        assert_eq!(f, same, "identical signatures are interned");
+        assert_eq!(registry.get_function(f).unwrap().ret, Type::Class(animal_id));
+
+        // covariant return
+        let narrower = registry.add_function(FnSig { params: vec![Type::Int], ret: Type::Class(dog_id) });
+        assert!(Type::Function(narrower).is_assignable_to(Type::Function(f), &registry));
+        assert!(!Type::Function(f).is_assignable_to(Type::Function(narrower), &registry));
+
+        // contravariant parameters
+        let takes_animal = registry.add_function(FnSig { params: vec![Type::Class(animal_id)], ret: Type::Int });
+        let takes_dog = registry.add_function(FnSig { params: vec![Type::Class(dog_id)], ret: Type::Int });
+        assert!(Type::Function(takes_animal).is_assignable_to(Type::Function(takes_dog), &registry));
+        assert!(!Type::Function(takes_dog).is_assignable_to(Type::Function(takes_animal), &registry));
+
+        // arity and kind mismatches
+        let no_args = registry.add_function(FnSig { params: vec![], ret: Type::Int });
+        assert!(!Type::Function(no_args).is_assignable_to(Type::Function(takes_animal), &registry));
+        assert!(!Type::Function(no_args).is_assignable_to(Type::Int, &registry));
+    }
+
+    #[test]
+    fn test_overridden_method() {
+        let (mut registry, animal_id, dog_id) = animal_dog();
+
+        let make = registry.add_function(FnSig { params: vec![], ret: Type::Class(animal_id) });
+        let make_dog = registry.add_function(FnSig { params: vec![], ret: Type::Class(dog_id) });
+        let make_int = registry.add_function(FnSig { params: vec![Type::Int], ret: Type::Class(dog_id) });
+
+        let base_id = setup_class(&mut registry, "Base", vec![], vec![("make", Type::Function(make))]).unwrap();
+
+        // returning a subclass is a valid override and shadows the base method
+        let derived_id = setup_class(&mut registry, "Derived", vec![base_id], vec![("make", Type::Function(make_dog))]).unwrap();
+        let derived = registry.get_class(derived_id).unwrap();
+        assert_eq!(derived.accessble_properties["make"].inner_type, Type::Function(make_dog));
+        let shadowed = &derived.shadowed_properties["make"];
+        assert!(shadowed.iter().all(|p| p.source == base_id));
+
+        // and children inherit the override
+        let leaf_id = setup_class(&mut registry, "Leaf", vec![derived_id], vec![]).unwrap();
+        assert_eq!(registry.get_class(leaf_id).unwrap().accessble_properties["make"].source, derived_id);
+
+        // changing the arguments is not
+        let result = setup_class(&mut registry, "Broken", vec![base_id], vec![("make", Type::Function(make_int))]);
+        assert_eq!(result, Err(ClassError::IncompatibleOverride {
+            name: "make".to_string(),
+            parent_type: Type::Function(make),
+            child_type: Type::Function(make_int),
+        }));
+
+        // function tables survive serialization
+        let bytes = registry.serialize();
+        let reloaded = InMemoryRegistry::deserialize(&bytes).unwrap();
+        assert_eq!(reloaded.get_function(make_dog), registry.get_function(make_dog));
+        assert_eq!(reloaded.get_class(leaf_id), registry.get_class(leaf_id));
+    }
+
+    #[test]
+    fn test_all_properties() {
+        // Same hierarchy as test_complex_diamond_with_shadowing_and_clashing
+        //     A (prop1)
+        //    / \
+        //   B   C (prop1, prop2)
+        //  / \ /
+        // D   E (prop2)
+        //  \ /
+        //   F (prop3)
+        let mut registry = InMemoryRegistry::new();
+
+        let a_id = setup_class(&mut registry, "A", vec![], vec![("prop1", Type::Int)]).unwrap();
+        let b_id = setup_class(&mut registry, "B", vec![a_id], vec![]).unwrap();
+        let c_id = setup_class(&mut registry, "C", vec![a_id], vec![("prop1", Type::Int), ("prop2", Type::String)]).unwrap();
+        let d_id = setup_class(&mut registry, "D", vec![b_id], vec![]).unwrap();
+        let e_id = setup_class(&mut registry, "E", vec![b_id, c_id], vec![("prop2", Type::String)]).unwrap();
+        let f_id = setup_class(&mut registry, "F", vec![d_id, e_id], vec![("prop3", Type::Float)]).unwrap();
+
+        let all = registry.all_properties(f_id);
+        let statuses = |name: &str| -> Vec<PropertyStatus> {
+            all.iter().filter(|(n, _, _)| *n == name).map(|(_, _, s)| *s).collect()
+        };
+
+        // A's prop1 is both clashing (via D) and shadowed (by C on the way through E)
+        assert_eq!(statuses("prop1"), vec![PropertyStatus::Clashing, PropertyStatus::Clashing, PropertyStatus::Shadowed]);
+        assert_eq!(statuses("prop2"), vec![PropertyStatus::Accessible, PropertyStatus::Shadowed]);
+        assert_eq!(statuses("prop3"), vec![PropertyStatus::Accessible]);
+
+        let (_, prop3, _) = all.iter().find(|(n, _, _)| *n == "prop3").unwrap();
+        assert_eq!(prop3.source, f_id);
+
+        // sorted by name
+        let names: Vec<&str> = all.iter().map(|(n, _, _)| *n).collect();
+        assert_eq!(names, vec!["prop1", "prop1", "prop1", "prop2", "prop2", "prop3"]);
+
+        assert!(registry.all_properties(f_id + 100).is_empty());
+    }
+
+    #[test]
+    fn test_resolve_member() {
+        let mut registry = InMemoryRegistry::new();
+
+        let a_id = setup_class(&mut registry, "A", vec![], vec![("a1", Type::Int), ("name", Type::String)]).unwrap();
+        let b_id = setup_class(&mut registry, "B", vec![a_id], vec![("name", Type::String)]).unwrap();
+
+        let x_id = setup_class(&mut registry, "X", vec![], vec![("shared_name", Type::Int)]).unwrap();
+        let y_id = setup_class(&mut registry, "Y", vec![], vec![("shared_name", Type::Float)]).unwrap();
+        let z_id = setup_class(&mut registry, "Z", vec![x_id, y_id], vec![]).unwrap();
+
+        // inherited and own properties
+        match registry.resolve_member(b_id, "a1") {
+            MemberResolution::Found(prop) => assert_eq!(prop.source, a_id),
+            other => panic!("expected a1 to be found, got {other:?}"),
+        }
+        match registry.resolve_member(b_id, "name") {
+            MemberResolution::Found(prop) => assert_eq!(prop.source, b_id, "B's name shadows A's"),
+            other => panic!("expected name to be found, got {other:?}"),
+        }
+
+        // clashing
+        match registry.resolve_member(z_id, "shared_name") {
+            MemberResolution::Ambiguous(props) => {
+                let sources: HashSet<ClassID> = props.iter().map(|p| p.source).collect();
+                assert_eq!(sources, HashSet::from([x_id, y_id]));
+            }
+            other => panic!("expected shared_name to be ambiguous, got {other:?}"),
+        }
+
+        // missing name or class
+        assert_eq!(registry.resolve_member(b_id, "nope"), MemberResolution::NotFound);
+        assert_eq!(registry.resolve_member(z_id + 100, "a1"), MemberResolution::NotFound);
+    }
+
+    #[test]
+    fn test_register_all_reverse_diamond() {
+        // D is given first even though it needs B and C which need A
+        let mut registry = InMemoryRegistry::new();
+
+        let ids = registry.register_all(vec![
+            ClassDef { name: "D", parents: vec!["B", "C"], properties: vec![("d_prop", "A")], is_abstract: false },
+            ClassDef { name: "C", parents: vec!["A"], properties: vec![("c_prop", "string")], is_abstract: false },
+            ClassDef { name: "B", parents: vec!["A"], properties: vec![("b_prop", "float")], is_abstract: false },
+            ClassDef { name: "A", parents: vec![], properties: vec![("a_prop", "int"), ("link", "D")], is_abstract: false },
+        ]).unwrap();
+
+        let [d_id, c_id, b_id, a_id] = ids[..] else { panic!("expected 4 ids") };
+        assert_eq!(registry.get_class_id("D"), Some(d_id));
+
+        let d_meta = registry.get_class(d_id).unwrap();
+        assert_eq!(d_meta.parents, vec![b_id, c_id]);
+        assert!(d_meta.ancestors.contains(&a_id));
+        for name in ["a_prop", "b_prop", "c_prop", "d_prop", "link"] {
+            assert!(d_meta.accessble_properties.contains_key(name), "D should have {name}");
+        }
+        assert_eq!(d_meta.accessble_properties["d_prop"].inner_type, Type::Class(a_id));
+        assert_eq!(d_meta.accessble_properties["link"].inner_type, Type::Class(d_id));
+
+        // later batches can build on earlier ones
+        let more = registry.register_all(vec![
+            ClassDef { name: "E", parents: vec!["D"], properties: vec![], is_abstract: false },
+        ]).unwrap();
+        assert!(registry.is_subclass_of(more[0], a_id));
+    }
+
+    #[test]
+    fn test_register_all_errors() {
+        let mut registry = InMemoryRegistry::new();
+
+        assert_eq!(
+            registry.register_all(vec![
+                ClassDef { name: "A", parents: vec!["B"], properties: vec![], is_abstract: false },
+                ClassDef { name: "B", parents: vec!["C"], properties: vec![], is_abstract: false },
+                ClassDef { name: "C", parents: vec!["A"], properties: vec![], is_abstract: false },
+            ]),
+            Err(ClassError::CyclicInheritance)
+        );
+
+        assert_eq!(
+            registry.register_all(vec![
+                ClassDef { name: "A", parents: vec!["Missing"], properties: vec![], is_abstract: false },
+            ]),
+            Err(ClassError::UnknownClassName("Missing".to_string()))
+        );
+
+        assert_eq!(
+            registry.register_all(vec![
+                ClassDef { name: "A", parents: vec![], properties: vec![("x", "nope")], is_abstract: false },
+            ]),
+            Err(ClassError::UnknownTypeName("nope".to_string()))
+        );
+
+        assert_eq!(
+            registry.register_all(vec![
+                ClassDef { name: "A", parents: vec![], properties: vec![], is_abstract: false },
+                ClassDef { name: "A", parents: vec![], properties: vec![], is_abstract: false },
+            ]),
+            Err(ClassError::DuplicateClass("A".to_string()))
+        );
+
+        assert_eq!(
+            registry.register_all(vec![
+                ClassDef { name: "A", parents: vec![], properties: vec![("x", "int"), ("x", "float")], is_abstract: false },
+            ]),
+            Err(ClassError::DuplicateProperty("x".to_string()))
+        );
+
+        // none of the failed batches registered anything
+        assert!(registry.get_class_id("A").is_none());
+        assert!(registry.get_class_id("B").is_none());
+    }
+
+    #[test]
+    fn test_register_all_compound_types() {
+        let mut registry = InMemoryRegistry::new();
+        let ids = registry.register_all(vec![
+            ClassDef { name: "Bag", parents: vec![], properties: vec![("counts", "int[]"), ("pair", "(int, Foo)")], is_abstract: false },
+            ClassDef { name: "Foo", parents: vec![], properties: vec![("bags", "Bag[]")], is_abstract: false },
+        ]).unwrap();
+
+        let bag = registry.get_class(ids[0]).unwrap();
+        assert_eq!(bag.accessble_properties["counts"].inner_type, registry.get_type("int[]").unwrap());
+        assert_eq!(bag.accessble_properties["pair"].inner_type, registry.get_type("(int, Foo)").unwrap());
+        let foo = registry.get_class(ids[1]).unwrap();
+        assert_eq!(foo.accessble_properties["bags"].inner_type, registry.get_type("Bag[]").unwrap());
+
+        assert_eq!(
+            registry.register_all(vec![
+                ClassDef { name: "Bad", parents: vec![], properties: vec![("x", "(int, nope)[]")], is_abstract: false },
+            ]),
+            Err(ClassError::UnknownTypeName("(int, nope)[]".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_register_all_rolls_back() {
+        let mut registry = InMemoryRegistry::new();
+        let base_id = setup_class(&mut registry, "Base", vec![], vec![("x", Type::String)]).unwrap();
+        // Later is only reserved, the batch defines it
+        let later_id = registry.add_class_id("Later");
+
+        let batch = |x_type| vec![
+            ClassDef { name: "Mid", parents: vec!["Base"], properties: vec![("m", "int")], is_abstract: false },
+            ClassDef { name: "Later", parents: vec!["Mid"], properties: vec![("x", x_type)], is_abstract: false },
+        ];
+        assert!(matches!(registry.register_all(batch("int")), Err(ClassError::IncompatibleOverride { .. })));
+
+        // Mid is gone again, Later is back to being only reserved
+        assert_eq!(registry.get_class_id("Mid"), None);
+        assert_eq!(registry.get_class_id("Later"), Some(later_id));
+        assert!(registry.get_class(later_id).is_none());
+        assert_eq!(registry.get_property_id("x", later_id), None);
+        assert_eq!(registry.children(base_id), vec![]);
+
+        // so the fixed batch can be submitted as is
+        let ids = registry.register_all(batch("string")).unwrap();
+        assert_eq!(ids[1], later_id);
+        assert!(registry.is_subclass_of(later_id, base_id));
+        assert_eq!(registry.get_class(later_id).unwrap().accessble_properties["x"].source, later_id);
+    }
+
+    #[test]
+    fn test_counts() {
+        let mut registry = InMemoryRegistry::new();
+
+        // just Object
+        assert_eq!(registry.class_count(), 1);
+        assert_eq!(registry.reserved_class_count(), 1);
+        assert_eq!(registry.property_count(), 0);
+
+        let a_id = setup_class(&mut registry, "A", vec![], vec![("a1", Type::Int), ("a2", Type::Float)]).unwrap();
+        assert_eq!(registry.class_count(), 2);
+        assert_eq!(registry.reserved_class_count(), 2);
+        assert_eq!(registry.property_count(), 2);
+
+        // reserving an id or a property slot doesn't count as defining it
+        let b_id = registry.add_class_id("B");
+        let b1_id = registry.add_property_id("b1", b_id);
+        assert_eq!(registry.class_count(), 2);
+        assert_eq!(registry.reserved_class_count(), 3);
+        assert_eq!(registry.property_count(), 2);
+
+        registry.add_property(b1_id, Property { id: b1_id, inner_type: Type::String, source: b_id, default: None }).unwrap();
+        let b1 = *registry.get_property(b1_id).unwrap();
+        let meta = ClassMeta::new(&registry, b_id, vec![a_id], HashMap::from([("b1", b1)])).unwrap();
+        registry.add_class(b_id, meta).unwrap();
+        assert_eq!(registry.class_count(), 3);
+        assert_eq!(registry.reserved_class_count(), 3);
+        assert_eq!(registry.property_count(), 3);
+
+        registry.remove_class(b_id).unwrap();
+        assert_eq!(registry.class_count(), 2);
+        assert_eq!(registry.reserved_class_count(), 2);
+        assert_eq!(registry.property_count(), 2);
+    }
+
+    #[test]
+    fn test_tuple_types() {
+        let (mut registry, _, _) = animal_dog();
+
+        // get_type only finds tuples that were already registered
+        assert_eq!(registry.get_type("(int, string)"), None);
+        let pair = registry.parse_type("(int, string)").unwrap();
+        let Type::Tuple(pair_id) = pair else { panic!("expected a tuple, got {pair:?}") };
+        assert_eq!(registry.get_tuple(pair_id).unwrap(), &[Type::Int, Type::String]);
+        assert_eq!(registry.get_type("( int,string )"), Some(pair));
+        assert_eq!(registry.parse_type("(int, string)"), Some(pair), "tuples are interned");
+
+        // nesting and the empty tuple
+        let nested = registry.parse_type("(float, (int, string))").unwrap();
+        let Type::Tuple(nested_id) = nested else { panic!("expected a tuple") };
+        assert_eq!(registry.get_tuple(nested_id).unwrap(), &[Type::Float, pair]);
+        let unit = registry.parse_type("()").unwrap();
+        assert!(matches!(unit, Type::Tuple(_)));
+
+        // bad elements fail the whole parse
+        assert_eq!(registry.parse_type("(int, nope)"), None);
+        assert_eq!(registry.parse_type("(int, (float)"), None);
+
+        // elementwise assignability
+        let animals = registry.parse_type("(Animal, int)").unwrap();
+        let dogs = registry.parse_type("(Dog, int)").unwrap();
+        let longer = registry.parse_type("(Dog, int, int)").unwrap();
+        assert!(dogs.is_assignable_to(animals, &registry));
+        assert!(!animals.is_assignable_to(dogs, &registry));
+        assert!(!longer.is_assignable_to(animals, &registry));
+        assert!(!pair.is_assignable_to(Type::Int, &registry));
+
+        // the tuple table survives serialization
+        let bytes = registry.serialize();
+        let reloaded = InMemoryRegistry::deserialize(&bytes).unwrap();
+        assert_eq!(reloaded.get_type("(Dog, int)"), Some(dogs));
+        assert_eq!(reloaded.get_tuple(nested_id), registry.get_tuple(nested_id));
+    }
+
+    #[test]
+    fn test_resolve_type_aliases() {
+        let mut registry = InMemoryRegistry::new();
+
+        let city_id = setup_class(&mut registry, "City", vec![], vec![]).unwrap();
+
+        // direct alias to a class and to a primitive
+        let town_id = registry.add_class_id("Town");
+        registry.add_alias(town_id, Type::Class(city_id)).unwrap();
+        let money_id = registry.add_class_id("Money");
+        registry.add_alias(money_id, Type::Float).unwrap();
+        assert_eq!(registry.resolve_type(Type::Class(town_id)), Type::Class(city_id));
+        assert_eq!(registry.resolve_type(Type::Class(money_id)), Type::Float);
+
+        // a chain of two
+        let village_id = registry.add_class_id("Village");
+        registry.add_alias(village_id, Type::Class(town_id)).unwrap();
+        assert_eq!(registry.resolve_type(Type::Class(village_id)), Type::Class(city_id));
+
+        // non aliases are left alone
+        assert_eq!(registry.resolve_type(Type::Class(city_id)), Type::Class(city_id));
+        assert_eq!(registry.resolve_type(Type::Int), Type::Int);
+
+        // real classes can't become aliases and aliases can't be redefined
+        assert!(registry.add_alias(city_id, Type::Int).is_err());
+        assert!(registry.add_alias(town_id, Type::Int).is_err());
+
+        // survives serialization
+        let bytes = registry.serialize();
+        let reloaded = InMemoryRegistry::deserialize(&bytes).unwrap();
+        assert_eq!(reloaded.resolve_type(Type::Class(village_id)), Type::Class(city_id));
+    }
+
+    #[test]
+    fn test_self_referential_alias() {
+        let mut registry = InMemoryRegistry::new();
+
+        let loop_id = registry.add_class_id("Loop");
+        registry.add_alias(loop_id, Type::Class(loop_id)).unwrap();
+        assert_eq!(registry.resolve_type(Type::Class(loop_id)), Type::Invalid);
+
+        let a_id = registry.add_class_id("A");
+        let b_id = registry.add_class_id("B");
+        registry.add_alias(a_id, Type::Class(b_id)).unwrap();
+        registry.add_alias(b_id, Type::Class(a_id)).unwrap();
+        assert_eq!(registry.resolve_type(Type::Class(a_id)), Type::Invalid);
+    }
+
+    #[test]
+    fn test_alias_depth_limit() {
+        // Alias0 -> Alias1 -> ... -> Alias64 -> int
+        let names: Vec<String> = (0..=MAX_ALIAS_DEPTH).map(|i| format!("Alias{i}")).collect();
+        let mut registry = InMemoryRegistry::new();
+        let ids: Vec<ClassID> = names.iter().map(|name| registry.add_class_id(name)).collect();
+        for pair in ids.windows(2) {
+            registry.add_alias(pair[0], Type::Class(pair[1])).unwrap();
+        }
+        registry.add_alias(ids[MAX_ALIAS_DEPTH], Type::Int).unwrap();
+
+        // exactly MAX_ALIAS_DEPTH aliases still resolve, one more is treated as a loop
+        assert_eq!(registry.resolve_type(Type::Class(ids[1])), Type::Int);
+        assert_eq!(registry.resolve_type(Type::Class(ids[0])), Type::Invalid);
+    }
+
+    #[test]
+    fn test_abstract_classes() {
+        let mut registry = InMemoryRegistry::new();
+
+        let shape_id = setup_abstract_class(&mut registry, "Shape", vec![], vec![("area", Type::Float)]).unwrap();
+        let circle_id = setup_class(&mut registry, "Circle", vec![shape_id], vec![("radius", Type::Float)]).unwrap();
+
+        assert!(registry.is_abstract(shape_id));
+        assert!(!registry.is_abstract(OBJECT_ID));
+        assert!(!registry.is_abstract(999));
+
+        // children are concrete by default and still see the abstract properties
+        assert!(!registry.is_abstract(circle_id));
+        assert!(registry.is_subclass_of(circle_id, shape_id));
+        assert!(registry.get_class(circle_id).unwrap().accessble_properties.contains_key("area"));
+
+        let ids = registry.register_all(vec![
+            ClassDef { name: "Animal", parents: vec![], properties: vec![("legs", "int")], is_abstract: true },
+            ClassDef { name: "Dog", parents: vec!["Animal"], properties: vec![], is_abstract: false },
+        ]).unwrap();
+        assert!(registry.is_abstract(ids[0]));
+        assert!(!registry.is_abstract(ids[1]));
+
+        let bytes = registry.serialize();
+        let reloaded = InMemoryRegistry::deserialize(&bytes).unwrap();
+        assert!(reloaded.is_abstract(shape_id));
+        assert!(reloaded.is_abstract(ids[0]));
+        assert!(!reloaded.is_abstract(circle_id));
+    }
+
+    #[test]
+    fn test_type_size() {
+        let mut registry = InMemoryRegistry::new();
+
+        assert_eq!(registry.type_size(Type::Int), 4);
+        assert_eq!(registry.type_size(Type::Float), 8);
+        assert_eq!(registry.type_size(Type::String), mem::size_of::<usize>());
+        assert_eq!(registry.type_size(Type::Invalid), 0);
+
+        let point_id = setup_class(&mut registry, "Point", vec![], vec![("x", Type::Int), ("y", Type::Float)]).unwrap();
+        assert_eq!(registry.type_size(Type::Class(point_id)), 12);
+
+        // inherited fields count too and nested classes are inline
+        let named_id = setup_class(&mut registry, "Named", vec![point_id], vec![("name", Type::String), ("origin", Type::Class(point_id))]).unwrap();
+        assert_eq!(registry.type_size(Type::Class(named_id)), 12 + mem::size_of::<usize>() + 12);
+
+        let pair = registry.parse_type("(int, float)").unwrap();
+        assert_eq!(registry.type_size(pair), 12);
+    }
+
+    #[test]
+    fn test_type_size_cycle() {
+        let mut registry = InMemoryRegistry::new();
+
+        let node_id = registry.add_class_id("Node");
+        setup_class(&mut registry, "Node", vec![], vec![("value", Type::Int), ("next", Type::Class(node_id))]).unwrap();
+        assert_eq!(registry.type_size(Type::Class(node_id)), 4 + mem::size_of::<usize>());
+    }
+
+    #[test]
+    fn test_add_property_checked() {
+        let mut registry = InMemoryRegistry::new();
+        let a_id = registry.add_class_id("A");
+        let prop_id = registry.add_property_id("x", a_id);
+        let prop = Property { id: prop_id, inner_type: Type::Int, source: a_id, default: None };
+
+        // stored under the wrong id
+        let other_id = registry.add_property_id("y", a_id);
+        assert_eq!(
+            registry.add_property_checked(other_id, prop),
+            Err(PropertyError::MismatchedId { expected: other_id, found: prop_id }),
+        );
+
+        // source that was never registered
+        assert_eq!(
+            registry.add_property_checked(prop_id, Property { source: 999, ..prop }),
+            Err(PropertyError::UnknownSource(999)),
+        );
+
+        // id that was never handed out
+        assert_eq!(
+            registry.add_property_checked(999, Property { id: 999, ..prop }),
+            Err(PropertyError::UnknownId(999)),
+        );
+
+        // nothing was stored by the failed attempts
+        assert!(!registry.get_property(prop_id).unwrap().inner_type.is_valid());
+
+        assert_eq!(registry.add_property_checked(prop_id, prop), Ok(()));
+        assert_eq!(registry.add_property_checked(prop_id, prop), Err(PropertyError::Duplicate));
+        assert_eq!(registry.get_property(prop_id), Some(&prop));
+
+        // a class that was removed is no longer a valid source
+        let gone_id = setup_class(&mut registry, "Gone", vec![], vec![]).unwrap();
+        registry.remove_class(gone_id).unwrap();
+        assert!(!registry.has_class_id(gone_id));
+        let late_id = registry.add_property_id("late", a_id);
+        assert_eq!(
+            registry.add_property_checked(late_id, Property { id: late_id, source: gone_id, ..prop }),
+            Err(PropertyError::UnknownSource(gone_id)),
+        );
+    }
+
+    #[test]
+    fn test_get_property_full() {
+        let mut registry = InMemoryRegistry::new();
+        let a_id = setup_class(&mut registry, "A", vec![], vec![("a1", Type::Int)]).unwrap();
+        let prop_id = registry.get_property_id("a1", a_id).unwrap();
+
+        let (prop, name, class_name) = registry.get_property_full(prop_id).unwrap();
+        assert_eq!(prop.source, a_id);
+        assert_eq!(name, "a1");
+        assert_eq!(class_name, "A");
+
+        // the source class has an id but no ClassMeta yet
+        let b_id = registry.add_class_id("B");
+        let b1 = create_property(&mut registry, "b1", b_id, Type::Float);
+        assert!(registry.get_property_full(b1.id).is_none());
+        assert!(registry.get_property_full(999).is_none());
+    }
+
+    #[test]
+    fn test_duplicate_def_as_error() {
+        let mut registry = InMemoryRegistry::new();
+        let a_id = setup_class(&mut registry, "A", vec![], vec![]).unwrap();
+        let meta = registry.get_class(a_id).unwrap().clone();
+
+        let err: Box<dyn std::error::Error> = registry.add_class(a_id, meta).unwrap_err().into();
+        assert_eq!(err.to_string(), "this id is already defined");
+    }
+
+    #[test]
+    fn test_property_origin() {
+        let mut registry = InMemoryRegistry::new();
+        let a_id = setup_class(&mut registry, "A", vec![], vec![("a1", Type::Int), ("a2", Type::String)]).unwrap();
+        let b_id = setup_class(&mut registry, "B", vec![a_id], vec![("b1", Type::Float), ("a2", Type::String)]).unwrap();
+        let c_id = setup_class(&mut registry, "C", vec![b_id], vec![]).unwrap();
+
+        assert_eq!(registry.property_origin(b_id, "b1"), Some(PropertyOrigin::Direct));
+        assert_eq!(registry.property_origin(b_id, "a1"), Some(PropertyOrigin::Inherited(a_id)));
+        // shadowing makes it direct again
+        assert_eq!(registry.property_origin(b_id, "a2"), Some(PropertyOrigin::Direct));
+        assert_eq!(registry.property_origin(c_id, "a2"), Some(PropertyOrigin::Inherited(b_id)));
+
+        assert_eq!(registry.property_origin(b_id, "missing"), None);
+        assert_eq!(registry.property_origin(999, "a1"), None);
+    }
+
+    #[test]
+    fn test_property_attributes() {
+        let mut registry = InMemoryRegistry::new();
+        let a_id = registry.add_class_id("A");
+        let name_id = registry.add_property_id("name", a_id);
+        let name = Property { id: name_id, inner_type: Type::String, source: a_id, default: None };
+        registry.add_property_with_attributes(name_id, name, &["readonly", "indexed"]).unwrap();
+        let age = create_property(&mut registry, "age", a_id, Type::Int);
+
+        assert!(registry.property_has_attr(name_id, "readonly"));
+        assert!(registry.property_has_attr(name_id, "indexed"));
+        assert!(!registry.property_has_attr(name_id, "hidden"));
+        assert!(!registry.property_has_attr(age.id, "readonly"));
+        assert_eq!(registry.get_attributes(name_id), vec!["indexed", "readonly"]);
+
+        assert!(registry.add_attribute(age.id, "hidden"));
+        assert!(!registry.add_attribute(age.id, "hidden"));
+        assert!(registry.property_has_attr(age.id, "hidden"));
+
+        // ids that were never handed out don't get a side table entry
+        assert!(!registry.add_attribute(999, "hidden"));
+        assert!(registry.get_attributes(999).is_empty());
+
+        let bytes = registry.serialize();
+        let reloaded = InMemoryRegistry::deserialize(&bytes).unwrap();
+        assert_eq!(reloaded.get_attributes(name_id), vec!["indexed", "readonly"]);
+        assert!(reloaded.property_has_attr(age.id, "hidden"));
+
+        registry.remove_property(name_id);
+        assert!(registry.get_attributes(name_id).is_empty());
+    }
+
+    #[test]
+    fn test_validate_class() {
+        let mut registry = InMemoryRegistry::new();
+        let a_id = setup_class(&mut registry, "A", vec![], vec![("a1", Type::Int), ("a2", Type::Int)]).unwrap();
+        let b_id = setup_class(&mut registry, "B", vec![a_id], vec![("a1", Type::Int), ("b1", Type::Float)]).unwrap();
+
+        assert_eq!(registry.validate_class(b_id, &ClassLimits::default()), Ok(()));
+
+        // a1 a2 b1 plus the shadowed a1
+        let limits = ClassLimits { max_properties: 3, max_ancestors: 8 };
+        assert_eq!(registry.validate_class(a_id, &limits), Ok(()));
+        assert_eq!(
+            registry.validate_class(b_id, &limits),
+            Err(ClassError::TooManyProperties { count: 4, limit: 3 }),
+        );
+
+        // B has A and Object
+        let limits = ClassLimits { max_properties: 100, max_ancestors: 1 };
+        assert_eq!(
+            registry.validate_class(b_id, &limits),
+            Err(ClassError::TooManyAncestors { count: 2, limit: 1 }),
+        );
+
+        assert_eq!(registry.validate_class(999, &limits), Err(ClassError::UnknownClass(999)));
+    }
+
+    #[test]
+    fn test_reresolve_descendants() {
+        let mut registry = InMemoryRegistry::new();
+        let a_id = setup_class(&mut registry, "A", vec![], vec![("a1", Type::Int)]).unwrap();
+        let b_id = setup_class(&mut registry, "B", vec![a_id], vec![("b1", Type::Float)]).unwrap();
+        let c_id = setup_class(&mut registry, "C", vec![b_id], vec![]).unwrap();
+        let other_id = setup_class(&mut registry, "Other", vec![], vec![]).unwrap();
+        let other_before = registry.get_class(other_id).unwrap().clone();
+
+        // edit A after its descendants were resolved
+        create_property(&mut registry, "extra", a_id, Type::String);
+        assert!(!registry.get_class(c_id).unwrap().accessble_properties.contains_key("extra"));
+
+        // reresolving only A leaves the grandchild stale
+        registry.reresolve(a_id).unwrap();
+        assert!(registry.get_class(a_id).unwrap().accessble_properties.contains_key("extra"));
+        assert!(!registry.get_class(c_id).unwrap().accessble_properties.contains_key("extra"));
+
+        registry.reresolve_descendants(a_id).unwrap();
+        let c_meta = registry.get_class(c_id).unwrap();
+        assert_eq!(c_meta.accessble_properties["extra"].source, a_id);
+        assert!(c_meta.accessble_properties.contains_key("a1"));
+        assert!(c_meta.accessble_properties.contains_key("b1"));
+        assert_eq!(registry.get_class(other_id).unwrap(), &other_before);
+
+        assert_eq!(registry.reresolve(999), Err(ClassError::UnknownClass(999)));
+    }
+
+    #[test]
+    fn test_reresolve_keeps_policy() {
+        let mut registry = InMemoryRegistry::new();
+        let x_id = setup_class(&mut registry, "X", vec![], vec![("x", Type::Int)]).unwrap();
+        let y_id = setup_class(&mut registry, "Y", vec![], vec![("x", Type::Int)]).unwrap();
+        let z_id = registry.add_class_id("Z");
+        let meta = ClassMeta::new_with_policy(&registry, z_id, vec![x_id, y_id], HashMap::new(), ClashPolicy::FirstParent).unwrap();
+        assert_eq!(meta.clash_policy, ClashPolicy::FirstParent);
+        registry.add_class(z_id, meta).unwrap();
+
+        registry.reresolve(z_id).unwrap();
+        let z_meta = registry.get_class(z_id).unwrap();
+        assert_eq!(z_meta.clash_policy, ClashPolicy::FirstParent);
+        assert_eq!(z_meta.accessble_properties["x"].source, x_id);
+        assert!(!z_meta.clashing_properties.contains_key("x"));
+
+        // the policy survives a round trip too
+        let bytes = registry.serialize();
+        let reloaded = InMemoryRegistry::deserialize(&bytes).unwrap();
+        assert_eq!(reloaded.get_class(z_id), registry.get_class(z_id));
+    }
+
+    #[test]
+    fn test_structural_hash() {
+        let build = |registry: &mut InMemoryRegistry<'static>, name: &'static str| {
+            setup_class(registry, name, vec![], vec![("x", Type::Int), ("label", Type::String)]).unwrap()
+        };
+
+        // same registry, different ids
+        let mut registry = InMemoryRegistry::new();
+        let a_id = build(&mut registry, "A");
+        let b_id = build(&mut registry, "B");
+        let hash_a = registry.get_class(a_id).unwrap().structural_hash();
+        assert_eq!(hash_a, registry.get_class(b_id).unwrap().structural_hash());
+
+        // independent registry where the class got a different id
+        let mut other = InMemoryRegistry::new();
+        other.add_class_id("Filler");
+        let c_id = build(&mut other, "C");
+        assert_ne!(a_id, c_id);
+        assert_eq!(hash_a, other.get_class(c_id).unwrap().structural_hash());
+
+        // a differing property type
+        let d_id = setup_class(&mut registry, "D", vec![], vec![("x", Type::Float), ("label", Type::String)]).unwrap();
+        assert_ne!(hash_a, registry.get_class(d_id).unwrap().structural_hash());
+
+        // same properties but inherited from A
+        let e_id = setup_class(&mut registry, "E", vec![a_id], vec![]).unwrap();
+        assert_ne!(hash_a, registry.get_class(e_id).unwrap().structural_hash());
+
+        // the hash is fixed, not seeded per process
+        let mut state = Fnv1a::default();
+        state.write(b"a");
+        assert_eq!(state.finish(), 0xaf63_dc4c_8601_ec8c);
+
+        // the full comparison agrees with the hash
+        let a = registry.get_class(a_id).unwrap();
+        assert!(a.same_structure(other.get_class(c_id).unwrap()));
+        assert!(!a.same_structure(registry.get_class(d_id).unwrap()));
+        assert!(!a.same_structure(registry.get_class(e_id).unwrap()));
+    }
+
+    #[test]
+    fn test_array_types() {
+        let mut registry = InMemoryRegistry::new();
+        let base_id = setup_class(&mut registry, "ClassBase", vec![], vec![]).unwrap();
+        setup_class(&mut registry, "ClassA", vec![base_id], vec![]).unwrap();
+
+        // get_type only finds arrays that were already registered
+        assert_eq!(registry.get_type("int[]"), None);
+        let ints = registry.parse_type("int[]").unwrap();
+        let Type::Array(ints_id) = ints else { panic!("expected an array, got {ints:?}") };
+        assert_eq!(registry.get_array(ints_id), Some(Type::Int));
+        assert_eq!(registry.get_type(" int[] "), Some(ints));
+        assert_eq!(registry.parse_type("int[]"), Some(ints), "arrays are interned");
+
+        // nesting and mixing with tuples
+        let grid = registry.parse_type("int[][]").unwrap();
+        let Type::Array(grid_id) = grid else { panic!("expected an array") };
+        assert_eq!(registry.get_array(grid_id), Some(ints));
+        let pairs = registry.parse_type("(int, float)[]").unwrap();
+        let Type::Array(pairs_id) = pairs else { panic!("expected an array") };
+        assert_eq!(registry.get_array(pairs_id), registry.get_type("(int, float)"));
+        assert_eq!(registry.parse_type("nope[]"), None);
+
+        // arrays are invariant in their element
+        let bases = registry.parse_type("ClassBase[]").unwrap();
+        let subs = registry.parse_type("ClassA[]").unwrap();
+        assert!(subs.is_assignable_to(subs, &registry));
+        assert!(!subs.is_assignable_to(bases, &registry));
+        assert!(!bases.is_assignable_to(subs, &registry));
+        assert!(!ints.is_assignable_to(grid, &registry));
+        assert!(!ints.is_assignable_to(Type::Int, &registry));
+
+        let bytes = registry.serialize();
+        let reloaded = InMemoryRegistry::deserialize(&bytes).unwrap();
+        assert_eq!(reloaded.get_type("int[][]"), Some(grid));
+        assert_eq!(reloaded.get_type("ClassA[]"), Some(subs));
+    }
+
+    #[test]
+    fn test_renumber_by_name() {
+        let defs = [
+            ClassDef { name: "Animal", parents: vec![], properties: vec![("legs", "int"), ("name", "string")], is_abstract: false },
+            ClassDef { name: "Dog", parents: vec!["Animal"], properties: vec![("owner", "Person"), ("tags", "string")], is_abstract: false },
+            ClassDef { name: "Person", parents: vec![], properties: vec![("pet", "Animal")], is_abstract: false },
+        ];
+
+        let mut forward = InMemoryRegistry::new();
+        forward.register_all(defs.to_vec()).unwrap();
+        let mut backward = InMemoryRegistry::new();
+        backward.register_all(defs.iter().rev().cloned().collect()).unwrap();
+        assert_ne!(forward.get_class_id("Animal"), backward.get_class_id("Animal"));
+
+        forward.renumber_by_name();
+        backward.renumber_by_name();
+        for (name, id) in [("Object", OBJECT_ID), ("Animal", 1), ("Dog", 2), ("Person", 3)] {
+            assert_eq!(forward.get_class_id(name), Some(id));
+            assert_eq!(backward.get_class_id(name), Some(id));
+        }
+        assert_eq!(forward.serialize(), backward.serialize());
+        assert_eq!(forward.get_cur_class_id(), 4);
+
+        // every reference was rewritten along with the ids
+        let dog_id = forward.get_class_id("Dog").unwrap();
+        let dog = forward.get_class(dog_id).unwrap();
+        assert_eq!(dog.parents, vec![1]);
+        assert_eq!(dog.ancestors, HashSet::from([OBJECT_ID, 1]));
+        assert_eq!(dog.accessble_properties["legs"].source, 1);
+        assert_eq!(dog.accessble_properties["owner"].inner_type, Type::Class(3));
+        let owner_id = forward.get_property_id("owner", dog_id).unwrap();
+        assert_eq!(forward.get_property(owner_id).unwrap().id, owner_id);
+        assert!(forward.is_subclass_of(dog_id, 1));
+    }
+
+    #[test]
+    fn test_renumber_side_tables() {
+        let build = |types: &[&str], extra: bool| {
+            let mut registry = InMemoryRegistry::new();
+            if extra {
+                // leaves gaps in both counters and interns the functions the other way around
+                let gone = setup_class(&mut registry, "Gone", vec![], vec![("g", Type::Int)]).unwrap();
+                registry.remove_class(gone).unwrap();
+            }
+            let a_id = setup_class(&mut registry, "A", vec![], vec![]).unwrap();
+            let b_id = setup_class(&mut registry, "B", vec![a_id], vec![]).unwrap();
+            for t in types {
+                registry.parse_type(t).unwrap();
+            }
+            let list = registry.get_type("(int, A)[]").unwrap();
+            let mut sigs = vec![FnSig { params: vec![Type::Class(b_id)], ret: Type::Int }, FnSig { params: vec![], ret: list }];
+            if extra {
+                sigs.reverse();
+            }
+            for sig in sigs {
+                registry.add_function(sig);
+            }
+            registry.renumber_by_name();
+            registry
+        };
+
+        let first = build(&["(int, A)[]", "B[]", "(B[], float)"], false);
+        let second = build(&["(B[], float)", "B[]", "(int, A)[]"], true);
+        assert_eq!(first.serialize(), second.serialize());
+        assert_eq!(second.get_cur_class_id(), 3);
+        assert_eq!(second.get_cur_property_id(), 0);
+
+        // the types still mean what they did
+        let pair = first.get_type("(B[], float)").unwrap();
+        let Type::Tuple(pair_id) = pair else { panic!("expected a tuple, got {pair:?}") };
+        assert_eq!(first.get_tuple(pair_id).unwrap(), &[first.get_type("B[]").unwrap(), Type::Float]);
+    }
+
+    #[test]
+    fn test_merge_disjoint() {
+        let mut target = InMemoryRegistry::new();
+        let animal_id = setup_class(&mut target, "Animal", vec![], vec![("legs", Type::Int)]).unwrap();
+        target.parse_type("(int, string)").unwrap();
+
+        let mut module = InMemoryRegistry::new();
+        let person_id = setup_class(&mut module, "Person", vec![], vec![("name", Type::String)]).unwrap();
+        let pets = module.parse_type("Person[]").unwrap();
+        let pair = module.parse_type("(Person[], float)").unwrap();
+        let student_id = setup_class(&mut module, "Student", vec![person_id], vec![("friends", pets), ("grade", pair)]).unwrap();
+
+        let moved = target.merge(module).unwrap();
+        assert_eq!(moved[&OBJECT_ID], OBJECT_ID);
+        let new_person = target.get_class_id("Person").unwrap();
+        let new_student = target.get_class_id("Student").unwrap();
+        assert_eq!(moved[&person_id], new_person);
+        assert_eq!(moved[&student_id], new_student);
+        assert!(new_person != animal_id && new_student != animal_id);
+
+        let student = target.get_class(new_student).unwrap();
+        assert_eq!(student.parents, vec![new_person]);
+        assert_eq!(student.accessble_properties["name"].source, new_person);
+        assert_eq!(target.type_size(Type::Class(new_student)), mem::size_of::<usize>() * 3 + 8);
+        assert_eq!(student.accessble_properties["friends"].inner_type, target.get_type("Person[]").unwrap());
+        assert_eq!(student.accessble_properties["grade"].inner_type, target.get_type("(Person[], float)").unwrap());
+        let grade_id = target.get_property_id("grade", new_student).unwrap();
+        assert_eq!(target.get_property(grade_id).unwrap().id, grade_id);
+        assert!(target.is_subclass_of(new_student, new_person));
+        assert!(target.get_class(animal_id).unwrap().accessble_properties.contains_key("legs"));
+
+        // new ids keep coming after the merged ones
+        let next = target.add_class_id("Next");
+        assert!(next > new_person && next > new_student);
+    }
+
+    #[test]
+    fn test_merge_colliding_names() {
+        let mut target = InMemoryRegistry::new();
+        let point_id = setup_class(&mut target, "Point", vec![], vec![("x", Type::Int), ("y", Type::Int)]).unwrap();
+        let x_id = target.get_property_id("x", point_id).unwrap();
+
+        // structurally identical classes are merged
+        let mut same = InMemoryRegistry::new();
+        same.add_class_id("Filler");
+        let other_point = setup_class(&mut same, "Point", vec![], vec![("y", Type::Int), ("x", Type::Int)]).unwrap();
+        setup_class(&mut same, "Point3", vec![other_point], vec![("z", Type::Int)]).unwrap();
+        let moved = target.merge(same).unwrap();
+        assert_eq!(moved[&other_point], point_id);
+        let point3 = target.get_class_id("Point3").unwrap();
+        let point3_meta = target.get_class(point3).unwrap();
+        assert_eq!(point3_meta.parents, vec![point_id]);
+        assert_eq!(point3_meta.accessble_properties["x"].id, x_id);
+
+        // a class with the same name but a different shape is refused
+        let mut clash = InMemoryRegistry::new();
+        setup_class(&mut clash, "Point", vec![], vec![("x", Type::Float)]).unwrap();
+        setup_class(&mut clash, "Other", vec![], vec![]).unwrap();
+        assert_eq!(target.merge(clash), Err(ClassError::DuplicateClass("Point".to_string())));
+        assert_eq!(target.get_class_id("Other"), None);
+        assert_eq!(target.get_class(point_id).unwrap().accessble_properties["x"].inner_type, Type::Int);
+    }
+
+    #[test]
+    fn test_merge_failure_changes_nothing() {
+        let mut target = InMemoryRegistry::new();
+        setup_class(&mut target, "Point", vec![], vec![("x", Type::Int)]).unwrap();
+        let before = target.serialize();
+
+        // the clash is only found after other's tuple and array types were looked at
+        let mut clash = InMemoryRegistry::new();
+        let pair = clash.parse_type("(float, string)[]").unwrap();
+        setup_class(&mut clash, "Point", vec![], vec![("x", pair)]).unwrap();
+        assert_eq!(target.merge(clash), Err(ClassError::DuplicateClass("Point".to_string())));
+        assert_eq!(target.serialize(), before);
+
+        // a class other defines that is only reserved here would pick up our reserved properties
+        let pending_id = target.add_class_id("Pending");
+        target.add_property_id("p", pending_id);
+        let mut module = InMemoryRegistry::new();
+        setup_class(&mut module, "Pending", vec![], vec![("p", Type::String)]).unwrap();
+        assert_eq!(target.merge(module), Err(ClassError::DuplicateClass("Pending".to_string())));
+        assert!(target.get_class(pending_id).is_none());
+    }
+
+    #[test]
+    fn test_merge_id_space_exhausted() {
+        let module = || {
+            let mut module = InMemoryRegistry::new();
+            setup_class(&mut module, "A", vec![], vec![("a1", Type::Int)]).unwrap();
+            module
+        };
+
+        // Object is shared so only A needs a fresh id, and the last one can't be handed out
+        let mut target = InMemoryRegistry::new();
+        target.next_class_id = u32::MAX;
+        assert_eq!(target.merge(module()), Err(ClassError::IdSpaceExhausted));
+        assert_eq!(target.next_class_id, u32::MAX);
+        assert_eq!(target.get_class_id("A"), None);
+
+        let mut target = InMemoryRegistry::new();
+        target.next_property_id = u32::MAX;
+        assert_eq!(target.merge(module()), Err(ClassError::IdSpaceExhausted));
+        assert_eq!(target.next_property_id, u32::MAX);
+        assert_eq!(target.get_class_id("A"), None);
+    }
+
+    #[test]
+    fn test_ancestors_ordered() {
+        let (mut registry, a_id, b_id, c_id, d_id) = diamond();
+
+        assert_eq!(registry.ancestors_ordered(OBJECT_ID), Ok(vec![OBJECT_ID]));
+        assert_eq!(registry.ancestors_ordered(b_id), Ok(vec![b_id, a_id, OBJECT_ID]));
+        assert_eq!(registry.ancestors_ordered(d_id), Ok(vec![d_id, b_id, c_id, a_id, OBJECT_ID]));
+
+        // declaration order decides between siblings
+        let swapped_id = setup_class(&mut registry, "Swapped", vec![c_id, b_id], vec![]).unwrap();
+        assert_eq!(registry.ancestors_ordered(swapped_id), Ok(vec![swapped_id, c_id, b_id, a_id, OBJECT_ID]));
+
+        // an ancestor listed again after its subclass is fine, before it there is no linearization
+        let e_id = setup_class(&mut registry, "E", vec![d_id, a_id], vec![]).unwrap();
+        assert_eq!(registry.ancestors_ordered(e_id), Ok(vec![e_id, d_id, b_id, c_id, a_id, OBJECT_ID]));
+        let bad_id = setup_class(&mut registry, "Bad", vec![a_id, d_id], vec![]).unwrap();
+        assert_eq!(registry.ancestors_ordered(bad_id), Err(ClassError::InconsistentHierarchy(bad_id)));
+
+        // a wider diamond on top of the first one
+        let x_id = setup_class(&mut registry, "X", vec![], vec![]).unwrap();
+        let f_id = setup_class(&mut registry, "F", vec![c_id, x_id], vec![]).unwrap();
+        let g_id = setup_class(&mut registry, "G", vec![d_id, f_id], vec![]).unwrap();
+        assert_eq!(registry.ancestors_ordered(g_id), Ok(vec![g_id, d_id, b_id, f_id, c_id, a_id, x_id, OBJECT_ID]));
+
+        assert_eq!(registry.ancestors_ordered(999), Err(ClassError::UnknownClass(999)));
+    }
+
+    #[test]
+    fn test_property_same_shape() {
+        let a = Property { id: 1, inner_type: Type::Int, source: 3, default: Some(DefaultValue::Int(2)) };
+        let b = Property { id: 7, ..a };
+        assert!(a.same_shape(&b));
+        assert_ne!(a, b);
+
+        assert!(!a.same_shape(&Property { inner_type: Type::Float, ..b }));
+        assert!(!a.same_shape(&Property { source: 4, ..b }));
+        assert!(!a.same_shape(&Property { default: None, ..b }));
+    }
+
+    #[test]
+    fn test_rename_class() {
+        let mut registry = InMemoryRegistry::new();
+        let a_id = setup_class(&mut registry, "A", vec![], vec![("a1", Type::Int)]).unwrap();
+        let b_id = setup_class(&mut registry, "B", vec![a_id], vec![]).unwrap();
+        let a1 = registry.get_property_id("a1", a_id).unwrap();
+
+        registry.rename_class(a_id, "Base").unwrap();
+        assert_eq!(registry.get_class_id("A"), None);
+        assert_eq!(registry.get_class_id("Base"), Some(a_id));
+        assert_eq!(registry.get_type("Base"), Some(Type::Class(a_id)));
+        assert_eq!(registry.get_class_and_name(a_id).unwrap().1, "Base");
+        assert_eq!(registry.get_property_full(a1).unwrap().2, "Base");
+        assert!(registry.is_subclass_of(b_id, a_id));
+
+        // renaming to its own name is a no-op
+        assert_eq!(registry.rename_class(a_id, "Base"), Ok(()));
+
+        assert_eq!(registry.rename_class(b_id, "Base"), Err(RenameError::NameTaken("Base".to_string())));
+        assert_eq!(registry.get_class_and_name(b_id).unwrap().1, "B");
+        assert_eq!(registry.rename_class(999, "Z"), Err(RenameError::UnknownClass(999)));
+    }
+
+    #[test]
+    fn test_children_and_descendants() {
+        let mut registry = InMemoryRegistry::new();
+        let base_id = setup_class(&mut registry, "Base", vec![], vec![]).unwrap();
+        let b_id = setup_class(&mut registry, "B", vec![base_id], vec![]).unwrap();
+        let c_id = setup_class(&mut registry, "C", vec![base_id], vec![]).unwrap();
+        let d_id = setup_class(&mut registry, "D", vec![b_id, c_id], vec![]).unwrap();
+        let e_id = setup_class(&mut registry, "E", vec![d_id], vec![]).unwrap();
+        let other_id = setup_class(&mut registry, "Other", vec![], vec![]).unwrap();
+
+        assert_eq!(registry.children(base_id), vec![b_id, c_id]);
+        assert_eq!(registry.children(b_id), vec![d_id]);
+        assert_eq!(registry.children(e_id), vec![]);
+        assert_eq!(registry.descendants(base_id), vec![b_id, c_id, d_id, e_id]);
+        assert_eq!(registry.descendants(c_id), vec![d_id, e_id]);
+        assert_eq!(registry.children(OBJECT_ID), vec![base_id, other_id]);
+        assert_eq!(registry.descendants(999), vec![]);
+
+        // removal and renumbering keep the index in step
+        registry.remove_class(e_id).unwrap();
+        assert_eq!(registry.descendants(base_id), vec![b_id, c_id, d_id]);
+        registry.renumber_by_name();
+        let base_id = registry.get_class_id("Base").unwrap();
+        let d_id = registry.get_class_id("D").unwrap();
+        assert_eq!(registry.children(registry.get_class_id("C").unwrap()), vec![d_id]);
+        assert_eq!(registry.descendants(base_id).len(), 3);
+        assert_eq!(registry.remove_class(base_id), Err(ClassError::StillParentOf(registry.get_class_id("B").unwrap())));
+
+        let bytes = registry.serialize();
+        let reloaded = InMemoryRegistry::deserialize(&bytes).unwrap();
+        assert_eq!(reloaded.descendants(base_id), registry.descendants(base_id));
+    }
+
+    #[test]
+    fn test_resolve_member_cached() {
+        let mut registry = InMemoryRegistry::new();
+        let x_id = setup_class(&mut registry, "X", vec![], vec![("shared", Type::Int), ("x1", Type::Int)]).unwrap();
+        let y_id = setup_class(&mut registry, "Y", vec![], vec![("shared", Type::Float)]).unwrap();
+        let z_id = setup_class(&mut registry, "Z", vec![x_id, y_id], vec![]).unwrap();
+
+        for _ in 0..2 {
+            for name in ["shared", "x1", "missing"] {
+                for class in [x_id, z_id, 999] {
+                    assert_eq!(registry.resolve_member_cached(class, name), registry.resolve_member(class, name));
+                }
+            }
+        }
+        assert!(matches!(registry.resolve_member_cached(z_id, "shared"), MemberResolution::Ambiguous(_)));
+
+        // reresolving drops what was cached for the class
+        assert_eq!(registry.resolve_member_cached(z_id, "extra"), MemberResolution::NotFound);
+        let extra = create_property(&mut registry, "extra", x_id, Type::String);
+        registry.reresolve_descendants(x_id).unwrap();
+        assert_eq!(registry.resolve_member_cached(z_id, "extra"), MemberResolution::Found(extra));
+    }
+
+    #[test]
+    fn test_id_space_exhausted() {
+        let mut registry = InMemoryRegistry::new();
+        let a_id = registry.add_class_id("A");
+
+        registry.next_property_id = u32::MAX - 1;
+        assert_eq!(registry.try_add_property_id("x", a_id), Ok(u32::MAX - 1));
+        assert_eq!(registry.try_add_property_id("y", a_id), Err(RegistryError::IdSpaceExhausted));
+        // nothing was reserved by the failed call
+        assert_eq!(registry.get_property_id("y", a_id), None);
+        assert_eq!(registry.get_cur_property_id(), u32::MAX);
+
+        registry.next_class_id = u32::MAX;
+        assert_eq!(registry.try_add_class_id("B"), Err(RegistryError::IdSpaceExhausted));
+        assert_eq!(registry.get_class_id("B"), None);
+        // names that already have an id still resolve
+        assert_eq!(registry.try_add_class_id("A"), Ok(a_id));
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut registry = InMemoryRegistry::new();
+        let a_id = setup_class(&mut registry, "A", vec![], vec![("a1", Type::Int)]).unwrap();
+        setup_class(&mut registry, "B", vec![a_id], vec![("b1", Type::Float)]).unwrap();
+        registry.parse_type("(int, A)[]").unwrap();
+        registry.resolve_member_cached(a_id, "a1");
+
+        registry.clear();
+        assert_eq!(registry.class_count(), 1);
+        assert_eq!(registry.reserved_class_count(), 1);
+        assert_eq!(registry.property_count(), 0);
+        assert_eq!(registry.get_class_id("A"), None);
+        assert_eq!(registry.get_type("(int, float)"), None);
+        assert_eq!(registry.children(OBJECT_ID), vec![]);
+        assert_eq!(registry.get_class_id(OBJECT_NAME), Some(OBJECT_ID));
+
+        // numbering starts over as in a new registry
+        let c_id = setup_class(&mut registry, "C", vec![], vec![("c1", Type::Int)]).unwrap();
+        assert_eq!(c_id, 1);
+        assert_eq!(registry.get_property_id("c1", c_id), Some(0));
+        assert_eq!(registry.resolve_member_cached(c_id, "a1"), MemberResolution::NotFound);
+        assert_eq!(registry.serialize(), {
+            let mut fresh = InMemoryRegistry::new();
+            setup_class(&mut fresh, "C", vec![], vec![("c1", Type::Int)]).unwrap();
+            fresh.serialize()
+        });
+    }
+}